@@ -0,0 +1,132 @@
+//! Checksum algorithms used by checksum watches and rules.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::state::Endian;
+
+/// A checksum or CRC algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChecksumAlgo {
+    /// Additive 8-bit sum, wrapping.
+    Sum8,
+    /// XOR of all bytes.
+    Xor8,
+    /// CRC-8 (poly 0x07, init 0x00).
+    Crc8,
+    /// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF).
+    Crc16Ccitt,
+    /// CRC-16/MODBUS (reflected poly 0xA001, init 0xFFFF).
+    Crc16Modbus,
+    /// CRC-32 as used by Ethernet and zip (reflected poly 0xEDB88320).
+    Crc32,
+}
+
+impl ChecksumAlgo {
+    /// Every algorithm, in the order shown in pickers.
+    pub const ALL: [ChecksumAlgo; 6] = [
+        ChecksumAlgo::Sum8,
+        ChecksumAlgo::Xor8,
+        ChecksumAlgo::Crc8,
+        ChecksumAlgo::Crc16Ccitt,
+        ChecksumAlgo::Crc16Modbus,
+        ChecksumAlgo::Crc32,
+    ];
+
+    /// Size of the checksum in bytes.
+    pub fn width(&self) -> usize {
+        match self {
+            ChecksumAlgo::Sum8 | ChecksumAlgo::Xor8 | ChecksumAlgo::Crc8 => 1,
+            ChecksumAlgo::Crc16Ccitt | ChecksumAlgo::Crc16Modbus => 2,
+            ChecksumAlgo::Crc32 => 4,
+        }
+    }
+
+    /// Compute the checksum of `bytes`.
+    pub fn compute(&self, bytes: &[u8]) -> u32 {
+        match self {
+            ChecksumAlgo::Sum8 => bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) as u32,
+            ChecksumAlgo::Xor8 => bytes.iter().fold(0u8, |acc, b| acc ^ b) as u32,
+            ChecksumAlgo::Crc8 => {
+                let mut crc = 0u8;
+                for b in bytes {
+                    crc ^= b;
+                    for _ in 0..8 {
+                        crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+                    }
+                }
+                crc as u32
+            }
+            ChecksumAlgo::Crc16Ccitt => {
+                let mut crc = 0xFFFFu16;
+                for b in bytes {
+                    crc ^= (*b as u16) << 8;
+                    for _ in 0..8 {
+                        crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+                    }
+                }
+                crc as u32
+            }
+            ChecksumAlgo::Crc16Modbus => {
+                let mut crc = 0xFFFFu16;
+                for b in bytes {
+                    crc ^= *b as u16;
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+                    }
+                }
+                crc as u32
+            }
+            ChecksumAlgo::Crc32 => {
+                let mut crc = 0xFFFF_FFFFu32;
+                for b in bytes {
+                    crc ^= *b as u32;
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+                    }
+                }
+                !crc
+            }
+        }
+    }
+
+    /// Compute the checksum of `bytes` as zero-padded uppercase hex of the algorithm's width.
+    pub fn compute_hex(&self, bytes: &[u8]) -> String {
+        format!("{:0width$X}", self.compute(bytes), width = self.width() * 2)
+    }
+
+    /// Byte order in which the checksum of `frame[start..=end]` is stored in the bytes
+    /// right after `end`, or `None` if they don't hold it.
+    pub fn trailer_order(&self, frame: &[u8], start: usize, end: usize) -> Option<Endian> {
+        let trailer = frame.get(end + 1..end + 1 + self.width())?;
+        self.stored_order(frame.get(start..=end)?, trailer)
+    }
+
+    /// Byte order in which `stored` holds the checksum of `bytes`, or `None` if it doesn't.
+    pub fn stored_order(&self, bytes: &[u8], stored: &[u8]) -> Option<Endian> {
+        let width = self.width();
+        if stored.len() != width { return None; }
+        let sum = self.compute(bytes).to_be_bytes();
+        let sum = &sum[4 - width..];
+        if stored == sum {
+            Some(Endian::Big)
+        } else if stored.iter().eq(sum.iter().rev()) {
+            Some(Endian::Little)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumAlgo::Sum8 => write!(f, "Sum-8"),
+            ChecksumAlgo::Xor8 => write!(f, "XOR-8"),
+            ChecksumAlgo::Crc8 => write!(f, "CRC-8"),
+            ChecksumAlgo::Crc16Ccitt => write!(f, "CRC-16/CCITT"),
+            ChecksumAlgo::Crc16Modbus => write!(f, "CRC-16/MODBUS"),
+            ChecksumAlgo::Crc32 => write!(f, "CRC-32"),
+        }
+    }
+}
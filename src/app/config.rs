@@ -0,0 +1,105 @@
+//! Saving and loading rules and framing settings as JSON.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::app::framing::FramingMode;
+use crate::app::state::{AppState, LabelMatchMode, LabelRule, WatchItem};
+use crate::app::suspects::SuspectRule;
+
+/// File loaded on startup from the app's data directory.
+pub const DEFAULT_CONFIG_FILE: &str = "config.json";
+
+/// Rules and framing settings written to a config file.
+///
+/// Violation counters and compiled regexes are not stored; `apply` rebuilds the regexes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistedConfig {
+    pub watch_items: Vec<WatchItem>,
+    pub label_rules: Vec<LabelRule>,
+    pub suspect_rules: Vec<SuspectRule>,
+    pub start_pattern: String,
+    pub start_pattern_ascii: bool,
+    pub end_pattern: String,
+    pub end_pattern_ascii: bool,
+    pub framing_mode: FramingMode,
+    pub unit_size: usize,
+    /// `(name, hex)` Send presets.
+    pub send_presets: Vec<(String, String)>,
+}
+
+impl Default for PersistedConfig {
+    fn default() -> Self {
+        Self::from_state(&AppState::default())
+    }
+}
+
+impl PersistedConfig {
+    /// Take the persisted part of `state`.
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            watch_items: state.engine.watch_items.clone(),
+            label_rules: state.engine.label_rules.clone(),
+            suspect_rules: state.engine.suspect_rules.clone(),
+            start_pattern: state.start_pattern.clone(),
+            start_pattern_ascii: state.start_pattern_ascii,
+            end_pattern: state.end_pattern.clone(),
+            end_pattern_ascii: state.end_pattern_ascii,
+            framing_mode: state.engine.framing_mode,
+            unit_size: state.engine.unit_size,
+            send_presets: state.send_presets.clone(),
+        }
+    }
+
+    /// Replace the rules and framing settings in `state`, closing any open edit forms.
+    pub fn apply(mut self, state: &mut AppState) -> Result<(), String> {
+        for rule in &mut self.label_rules {
+            for cond in rule.conditions.iter_mut().filter(|c| c.mode == LabelMatchMode::Regex) {
+                let re = Regex::new(&cond.pattern).map_err(|e| format!("label '{}': invalid regex: {}", rule.name, e))?;
+                cond.regex = Some(re);
+            }
+        }
+        state.engine.watch_items = self.watch_items;
+        state.engine.label_rules = self.label_rules;
+        state.engine.suspect_rules = self.suspect_rules;
+        for conn in &mut state.connections {
+            conn.suspect_runs.clear();
+        }
+        state.start_pattern = self.start_pattern;
+        state.start_pattern_ascii = self.start_pattern_ascii;
+        state.end_pattern = self.end_pattern;
+        state.end_pattern_ascii = self.end_pattern_ascii;
+        state.engine.framing_mode = self.framing_mode;
+        state.engine.unit_size = self.unit_size;
+        state.send_presets = self.send_presets;
+        state.edit_watch_idx = None;
+        state.edit_label_idx = None;
+        state.edit_suspect_idx = None;
+        state.refresh_delimiters();
+        state.refresh_annotations();
+        Ok(())
+    }
+}
+
+/// Write the rules and framing settings of `state` to `path` as pretty-printed JSON.
+pub fn save_config(state: &AppState, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&PersistedConfig::from_state(state)).map_err(|e| format!("serialize config: {}", e))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, json).map_err(|e| format!("write {}: {}", path.display(), e))
+}
+
+/// Read a config written by `save_config`.
+pub fn load_config(path: &Path) -> Result<PersistedConfig, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("parse {}: {}", path.display(), e))
+}
+
+/// Where the startup config lives, next to eframe's own storage.
+pub fn default_config_path() -> Option<PathBuf> {
+    eframe::storage_dir("ByteBuster").map(|dir| dir.join(DEFAULT_CONFIG_FILE))
+}
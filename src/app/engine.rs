@@ -0,0 +1,270 @@
+//! Capture pipeline: bytes in, framed and evaluated messages out.
+//!
+//! The GUI drives an `EngineState` through `process_bytes` like any headless caller, and
+//! only adds storage, statistics and display on top.
+use crate::app::framing::{flush_partial, frame_with_mode, FrameStatus, FramedMessage, FramingMode};
+use crate::app::state::{find_message_label, resolve_range, target_applies, LabelRule, WatchItem};
+use crate::app::suspects::{check_suspect_rule, ExpectedKind, Severity, SuspectRule};
+
+/// Framing configuration, rules and the partial-frame buffer for one byte stream.
+pub struct EngineState {
+    pub start_bytes: Vec<Vec<u8>>,
+    pub end_bytes: Vec<u8>,
+    pub framing_mode: FramingMode,
+    pub label_rules: Vec<LabelRule>,
+    pub watch_items: Vec<WatchItem>,
+    pub suspect_rules: Vec<SuspectRule>,
+    /// Bytes per group in hex and binary watch values.
+    pub unit_size: usize,
+    /// Bytes received but not yet part of a complete frame.
+    pub buffer: Vec<u8>,
+    /// Sequence position given to the next framed message.
+    pub next_index: u64,
+}
+
+impl Default for EngineState {
+    fn default() -> Self {
+        Self {
+            start_bytes: Vec::new(),
+            end_bytes: Vec::new(),
+            framing_mode: FramingMode::Delimited,
+            label_rules: Vec::new(),
+            watch_items: Vec::new(),
+            suspect_rules: Vec::new(),
+            unit_size: 1,
+            buffer: Vec::new(),
+            next_index: 0,
+        }
+    }
+}
+
+/// One watch evaluated against a message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchReading {
+    pub name: String,
+    /// Formatted value; `None` if the range is out of bounds.
+    pub text: Option<String>,
+    /// Numeric value for statistics, if the watch's view shows a number.
+    pub value: Option<f64>,
+}
+
+/// One framed message with everything the rules say about it.
+#[derive(Clone, Debug)]
+pub struct FramedResult {
+    pub bytes: Vec<u8>,
+    pub status: FrameStatus,
+    /// Bytes dropped between the previous frame and this frame's start delimiter.
+    pub skipped: usize,
+    /// Index into `start_bytes` of the delimiter that framed this message.
+    pub start_pattern: Option<usize>,
+    /// Position of this frame in the stream, counted from 0.
+    pub index: u64,
+    /// Name of the first matching label rule.
+    pub label: Option<String>,
+    /// Each watch that applies to this message.
+    pub watches: Vec<WatchReading>,
+    /// Suspect rule violations and notes, with the severity in force when the message arrived.
+    pub warnings: Vec<(Severity, String)>,
+}
+
+impl EngineState {
+    /// Cut complete messages from `buffer` with the configured framing.
+    pub fn frame(&self, buffer: &mut Vec<u8>) -> Vec<FramedMessage> {
+        frame_with_mode(buffer, self.framing_mode, &self.start_bytes, &self.end_bytes)
+    }
+
+    /// Evaluate `framed` as the next message in the stream.
+    pub fn evaluate(&mut self, framed: FramedMessage) -> FramedResult {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.evaluate_at(framed, index)
+    }
+
+    /// Evaluate `framed` as message `index`: label it, check its checksums, run the suspect
+    /// rules (counting violations) and read its watches.
+    pub fn evaluate_at(&mut self, framed: FramedMessage, index: u64) -> FramedResult {
+        let FramedMessage { bytes, skipped, start_pattern, mut status } = framed;
+        let label = find_message_label(&bytes, &self.label_rules);
+        let (warnings, checksum_failed) = self.check_suspects(&bytes, &label, index);
+        if checksum_failed {
+            status = FrameStatus::ChecksumFail;
+        }
+        let watches = self
+            .watch_items
+            .iter()
+            .filter(|w| target_applies(&w.target, &label, index))
+            .map(|w| {
+                let range = resolve_range(w.start_index, w.end_index, bytes.len()).map(|(s, e)| &bytes[s..=e]);
+                WatchReading {
+                    name: w.name.clone(),
+                    text: range.map(|r| w.format_value(r, self.unit_size, true)),
+                    value: range.and_then(|r| w.numeric_value(r)),
+                }
+            })
+            .collect();
+        FramedResult { bytes, status, skipped, start_pattern, index, label, watches, warnings }
+    }
+
+    /// Empty the buffer, evaluating whatever follows its first start delimiter as a `Flushed` frame.
+    ///
+    /// Used when no more data will complete the frame.
+    pub fn flush(&mut self) -> Option<FramedResult> {
+        let framed = flush_partial(&mut self.buffer, &self.start_bytes)?;
+        Some(self.evaluate(framed))
+    }
+
+    /// Run the suspect rules over a message, counting violations and runs of them, and
+    /// report whether a `Checksum` rule failed.
+    ///
+    /// A violation's severity is escalated by the run it extends, so it stays as it was
+    /// when the message arrived.
+    fn check_suspects(&mut self, msg: &[u8], label: &Option<String>, index: u64) -> (Vec<(Severity, String)>, bool) {
+        let mut warnings = Vec::new();
+        let mut checksum_failed = false;
+        for rule in &mut self.suspect_rules {
+            match check_suspect_rule(msg, label, index, rule) {
+                None => {
+                    if rule.enabled && target_applies(&rule.target, label, index) {
+                        rule.consecutive_violations = 0;
+                    }
+                }
+                // An out-of-range note is not a violation
+                Some(note) if !rule.in_range(msg.len()) => warnings.push(note),
+                Some((_, text)) => {
+                    rule.violation_count += 1;
+                    rule.consecutive_violations += 1;
+                    warnings.push((rule.effective_severity(), text));
+                    checksum_failed |= matches!(rule.expected_kind, ExpectedKind::Checksum { .. });
+                }
+            }
+        }
+        (warnings, checksum_failed)
+    }
+}
+
+/// Feed `bytes` into the stream and evaluate every message they complete.
+///
+/// Incomplete trailing data stays in `state.buffer` for the next call.
+pub fn process_bytes(state: &mut EngineState, bytes: &[u8]) -> Vec<FramedResult> {
+    state.buffer.extend_from_slice(bytes);
+    let mut buffer = std::mem::take(&mut state.buffer);
+    let frames = state.frame(&mut buffer);
+    state.buffer = buffer;
+    frames.into_iter().map(|framed| state.evaluate(framed)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::state::{build_label_rule, Endian, LabelConditionForm, WatchTarget, WatchView};
+    use crate::app::checksum::ChecksumAlgo;
+
+    const PING: [u8; 7] = [0xAA, 0x55, 0x01, 0x10, 0x00, 0x0D, 0x0A];
+    const BAD: [u8; 7] = [0xAA, 0x55, 0x02, 0x11, 0xFF, 0x0D, 0x0A];
+
+    /// `AA 55 .. 0D 0A` framing, a `PING` label on byte 2, a watch on byte 3 and a suspect
+    /// rule expecting `00` at byte 4 that escalates after two violations in a row.
+    fn engine() -> EngineState {
+        let cond = LabelConditionForm { range: "2".to_string(), value_hex: "01".to_string(), ..Default::default() };
+        EngineState {
+            start_bytes: vec![vec![0xAA, 0x55]],
+            end_bytes: vec![0x0D, 0x0A],
+            label_rules: vec![build_label_rule("PING", &[cond], [0, 0, 0]).unwrap()],
+            watch_items: vec![WatchItem {
+                name: "seq".to_string(),
+                start_index: 3,
+                end_index: 3,
+                view: WatchView::Integer { width: 1, signed: false },
+                endian: Endian::Little,
+                target: WatchTarget::All,
+                enum_names: Vec::new(),
+            }],
+            suspect_rules: vec![SuspectRule {
+                name: "tail".to_string(),
+                start_index: 4,
+                end_index: 4,
+                expected_kind: ExpectedKind::Hex,
+                expected_value: "00".to_string(),
+                target: WatchTarget::All,
+                severity: Severity::Warning,
+                trim: false,
+                enabled: true,
+                violation_count: 0,
+                escalate_after: 2,
+                consecutive_violations: 0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn severities(result: &FramedResult) -> Vec<Severity> {
+        result.warnings.iter().map(|(sev, _)| *sev).collect()
+    }
+
+    #[test]
+    fn frames_split_chunks() {
+        let mut state = engine();
+        let mut stream = vec![0xEE];
+        stream.extend_from_slice(&PING);
+        stream.extend_from_slice(&BAD);
+        stream.extend_from_slice(&BAD);
+        stream.extend_from_slice(&[0xAA, 0x55, 0x01]);
+        let results: Vec<FramedResult> = stream.chunks(3).flat_map(|chunk| process_bytes(&mut state, chunk)).collect();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].bytes, PING);
+        assert_eq!(results[0].skipped, 1);
+        assert_eq!(results[1].bytes, BAD);
+        assert_eq!(results.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(results[0].label.as_deref(), Some("PING"));
+        assert_eq!(results[1].label, None);
+        assert_eq!(results[0].watches, vec![WatchReading { name: "seq".to_string(), text: Some("16".to_string()), value: Some(16.0) }]);
+        assert_eq!(results[2].watches[0].text.as_deref(), Some("17"));
+        assert_eq!(results.iter().map(severities).collect::<Vec<_>>(), vec![vec![], vec![Severity::Warning], vec![Severity::Critical]]);
+        assert_eq!(state.buffer, [0xAA, 0x55, 0x01]);
+    }
+
+    #[test]
+    fn warnings_escalate_with_the_run() {
+        let mut state = engine();
+        let mut stream = BAD.to_vec();
+        stream.extend_from_slice(&BAD);
+        stream.extend_from_slice(&PING);
+        stream.extend_from_slice(&BAD);
+        let results = process_bytes(&mut state, &stream);
+
+        assert!(results[0].warnings[0].1.starts_with("tail:"));
+        assert_eq!(severities(&results[0]), vec![Severity::Warning]);
+        assert_eq!(severities(&results[1]), vec![Severity::Critical]);
+        assert_eq!(severities(&results[2]), Vec::new());
+        assert_eq!(severities(&results[3]), vec![Severity::Warning]);
+        assert_eq!(state.suspect_rules[0].violation_count, 3);
+        assert_eq!(state.suspect_rules[0].consecutive_violations, 1);
+    }
+
+    #[test]
+    fn failed_checksum_marks_the_frame() {
+        let mut state = engine();
+        state.suspect_rules[0].expected_kind = ExpectedKind::Checksum { algo: ChecksumAlgo::Xor8, over: (2, 3), stored_at: (4, 4) };
+        let results = process_bytes(&mut state, &[0xAA, 0x55, 0x01, 0x02, 0x03, 0x0D, 0x0A, 0xAA, 0x55, 0x01, 0x02, 0x04, 0x0D, 0x0A]);
+        assert_eq!(results[0].status, FrameStatus::Clean);
+        assert_eq!(results[1].status, FrameStatus::ChecksumFail);
+        assert_eq!(severities(&results[1]), vec![Severity::Warning]);
+    }
+
+    #[test]
+    fn partial_frame_stays_buffered() {
+        let mut state = engine();
+        assert!(process_bytes(&mut state, &PING[..4]).is_empty());
+        assert_eq!(state.buffer, PING[..4]);
+        let results = process_bytes(&mut state, &PING[4..]);
+        assert_eq!(results.len(), 1);
+        assert!(state.buffer.is_empty());
+
+        process_bytes(&mut state, &[0xAA, 0x55, 0x01, 0x10]);
+        let flushed = state.flush().unwrap();
+        assert_eq!(flushed.status, FrameStatus::Flushed);
+        assert_eq!(flushed.index, 1);
+        assert!(state.buffer.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Exporting captured messages to files.
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::state::ReceivedMessage;
+
+/// File format used when exporting captured messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One frame per line as uppercase hex.
+    HexLines,
+    /// All frames concatenated as raw bytes.
+    Binary,
+    /// CSV with index, time, label, length, repeat count, hex and note columns.
+    Csv,
+}
+
+impl ExportFormat {
+    /// Default file extension for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::HexLines => "txt",
+            ExportFormat::Binary => "bin",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::HexLines => write!(f, "Hex lines"),
+            ExportFormat::Binary => write!(f, "Binary"),
+            ExportFormat::Csv => write!(f, "CSV"),
+        }
+    }
+}
+
+/// Quote a CSV field if it contains separators, quotes, or newlines.
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write `messages` to `path` in the given `format`.
+pub fn export_messages(path: &Path, messages: &VecDeque<ReceivedMessage>, format: ExportFormat) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::HexLines => {
+            for msg in messages {
+                writeln!(out, "{}", hex::encode_upper(&msg.bytes))?;
+            }
+        }
+        ExportFormat::Binary => {
+            for msg in messages {
+                out.write_all(&msg.bytes)?;
+            }
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "index,time,label,length,repeat,hex,note")?;
+            for (i, msg) in messages.iter().enumerate() {
+                let label = msg.label.clone().unwrap_or_default();
+                let time = msg.wall_clock.format("%Y-%m-%dT%H:%M:%S%.6f%:z");
+                writeln!(out, "{},{},{},{},{},{},{}", i + 1, time, csv_field(&label), msg.bytes.len(), msg.repeat, hex::encode_upper(&msg.bytes), csv_field(&msg.note))?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// pcap link type for user-defined protocols (`LINKTYPE_USER0`).
+pub const LINKTYPE_USER0: u32 = 147;
+
+/// Write `packets` as a libpcap file, one record per framed message.
+pub fn export_pcap<'a>(path: &Path, packets: impl IntoIterator<Item = (SystemTime, &'a [u8])>, link_type: u32) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    // Global header: magic, version 2.4, GMT offset, sigfigs, snaplen, link type.
+    out.write_all(&0xA1B2_C3D4u32.to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&4u16.to_le_bytes())?;
+    out.write_all(&0i32.to_le_bytes())?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&65535u32.to_le_bytes())?;
+    out.write_all(&link_type.to_le_bytes())?;
+    for (ts, bytes) in packets {
+        let since_epoch = ts.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let len = bytes.len() as u32;
+        out.write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        out.write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(bytes)?;
+    }
+    out.flush()
+}
@@ -1,27 +1,339 @@
 //! Message framing utilities.
-/// Extract framed messages from `buffer` using `start` and `end` delimiters.
-pub fn frame_messages(buffer: &mut Vec<u8>, start: &[u8], end: &[u8]) -> Vec<Vec<u8>> {
+use std::fmt;
+
+use memchr::memmem;
+use serde::{Deserialize, Serialize};
+
+use crate::app::state::{decode_unsigned, Endian};
+
+/// How cleanly a message was cut from the stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// Bounded by the configured delimiters.
+    Clean,
+    /// Leftover bytes flushed without ever seeing the end delimiter.
+    Flushed,
+    /// No end delimiter is configured, so the frame ran to the end of the buffer.
+    NoEndDelimiter,
+    /// Failed a checksum expectation.
+    ChecksumFail,
+}
+
+impl fmt::Display for FrameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameStatus::Clean => write!(f, "Clean frame"),
+            FrameStatus::Flushed => write!(f, "Flushed: end delimiter never arrived"),
+            FrameStatus::NoEndDelimiter => write!(f, "No end delimiter configured"),
+            FrameStatus::ChecksumFail => write!(f, "Checksum failed"),
+        }
+    }
+}
+
+/// Where a frame's length is stored and what it counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LengthField {
+    /// Offset of the length field from the first byte of the frame (the start delimiter).
+    pub offset: usize,
+    /// Width of the length field in bytes: 1, 2 or 4.
+    pub size: usize,
+    pub endian: Endian,
+    /// The length counts the whole frame from its first byte; otherwise only the
+    /// payload after the length field.
+    pub includes_header: bool,
+}
+
+impl Default for LengthField {
+    /// One start byte followed by a 2-byte big-endian payload length.
+    fn default() -> Self {
+        Self { offset: 1, size: 2, endian: Endian::Big, includes_header: false }
+    }
+}
+
+/// How the byte stream is cut into messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FramingMode {
+    /// From a start delimiter to the next end delimiter.
+    Delimited,
+    /// From one start delimiter up to the byte before the next; the end pattern is ignored.
+    StartDelimited,
+    /// From a start delimiter for as many bytes as its length field says; the end pattern is ignored.
+    LengthPrefixed(LengthField),
+}
+
+impl fmt::Display for FramingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FramingMode::Delimited => write!(f, "Start and end"),
+            FramingMode::StartDelimited => write!(f, "Start only"),
+            FramingMode::LengthPrefixed(_) => write!(f, "Length prefix"),
+        }
+    }
+}
+
+/// A message cut from the stream, with what framing had to discard to find it.
+#[derive(Clone, Debug)]
+pub struct FramedMessage {
+    pub bytes: Vec<u8>,
+    /// Bytes dropped between the previous frame and this frame's start delimiter.
+    pub skipped: usize,
+    /// Index of the start delimiter that matched, if any were configured.
+    pub start_pattern: Option<usize>,
+    pub status: FrameStatus,
+}
+
+/// Find the earliest occurrence of any non-empty pattern, preferring the longest on a tie.
+///
+/// Returns `(position, pattern index)`.
+fn find_first_start(buffer: &[u8], starts: &[Vec<u8>]) -> Option<(usize, usize)> {
+    starts
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.is_empty())
+        .filter_map(|(i, p)| memmem::find(buffer, p).map(|pos| (pos, i)))
+        .min_by_key(|&(pos, i)| (pos, std::cmp::Reverse(starts[i].len())))
+}
+
+/// Extract framed messages from `buffer` using `end` and whichever of the alternative
+/// `starts` delimiters appears first. With no start delimiters a frame begins at the
+/// start of the buffer.
+pub fn frame_messages(buffer: &mut Vec<u8>, starts: &[Vec<u8>], end: &[u8]) -> Vec<FramedMessage> {
     let mut messages = Vec::new();
-    loop {
-        let start_pos = if start.is_empty() { Some(0) } else { buffer.windows(start.len()).position(|w| w == start) };
-        let s = match start_pos { Some(p) => p, None => break };
-        let after_start = s + start.len();
+    let any_start = starts.iter().any(|p| !p.is_empty());
+    while !buffer.is_empty() {
+        let (s, start_pattern) = if !any_start {
+            (0, None)
+        } else {
+            match find_first_start(buffer, starts) {
+                Some((p, i)) => (p, Some(i)),
+                None => break,
+            }
+        };
+        let start_len = start_pattern.map_or(0, |i| starts[i].len());
+        let after_start = s + start_len;
         if after_start > buffer.len() { break; }
         let end_pos = if end.is_empty() {
             Some(buffer.len())
         } else {
-            buffer[after_start..]
-                .windows(end.len())
-                .position(|w| w == end)
-                .map(|p| after_start + p)
+            memmem::find(&buffer[after_start..], end).map(|p| after_start + p)
         };
         let e = match end_pos { Some(p) => p, None => break };
         let msg_end = e + end.len();
         if msg_end <= buffer.len() {
-            messages.push(buffer[s..msg_end].to_vec());
+            let status = if end.is_empty() { FrameStatus::NoEndDelimiter } else { FrameStatus::Clean };
+            messages.push(FramedMessage { bytes: buffer[s..msg_end].to_vec(), skipped: s, start_pattern, status });
             buffer.drain(0..msg_end);
         } else { break; }
     }
     messages
 }
 
+/// Extract messages that run from one start delimiter to just before the next.
+///
+/// The last record stays buffered until another start delimiter arrives (or it is flushed
+/// with `flush_partial`). Without any start delimiters nothing is framed.
+pub fn frame_start_delimited(buffer: &mut Vec<u8>, starts: &[Vec<u8>]) -> Vec<FramedMessage> {
+    let mut messages = Vec::new();
+    while let Some((s, i)) = find_first_start(buffer, starts) {
+        let after_start = s + starts[i].len();
+        let Some((next, _)) = find_first_start(&buffer[after_start..], starts) else { break };
+        let msg_end = after_start + next;
+        messages.push(FramedMessage {
+            bytes: buffer[s..msg_end].to_vec(),
+            skipped: s,
+            start_pattern: Some(i),
+            status: FrameStatus::Clean,
+        });
+        buffer.drain(0..msg_end);
+    }
+    messages
+}
+
+/// Extract messages whose length is read from a field at a fixed offset from the start
+/// delimiter, so payloads may contain any bytes.
+///
+/// A frame stays buffered until all of it has arrived. Without start delimiters a frame
+/// begins at the start of the buffer. A length too small to cover the length field
+/// itself frames just the header, so the stream keeps moving.
+pub fn frame_length_prefixed(buffer: &mut Vec<u8>, starts: &[Vec<u8>], field: &LengthField) -> Vec<FramedMessage> {
+    let mut messages = Vec::new();
+    let any_start = starts.iter().any(|p| !p.is_empty());
+    let header_len = field.offset + field.size;
+    while !buffer.is_empty() {
+        let (s, start_pattern) = if !any_start {
+            (0, None)
+        } else {
+            match find_first_start(buffer, starts) {
+                Some((p, i)) => (p, Some(i)),
+                None => break,
+            }
+        };
+        let Some(len_bytes) = buffer.get(s + field.offset..s + header_len) else { break };
+        let Some(len) = decode_unsigned(len_bytes, field.endian) else { break };
+        let total = if field.includes_header { len as usize } else { header_len.saturating_add(len as usize) };
+        let msg_end = s.saturating_add(total.max(header_len));
+        if msg_end > buffer.len() { break; }
+        messages.push(FramedMessage { bytes: buffer[s..msg_end].to_vec(), skipped: s, start_pattern, status: FrameStatus::Clean });
+        buffer.drain(0..msg_end);
+    }
+    messages
+}
+
+/// Length of the longest proper suffix of `a` that is also a prefix of `b`.
+fn suffix_prefix_overlap(a: &[u8], b: &[u8]) -> usize {
+    (1..a.len().min(b.len())).rev().find(|&k| a[a.len() - k..] == b[..k]).unwrap_or(0)
+}
+
+/// Explain how a start delimiter and the end delimiter could be confused, if they can.
+///
+/// Flags identical patterns, one contained in the other, and patterns whose ends overlap
+/// (e.g. `AA 55` / `55 AA`), where a single run of bytes can read as both.
+pub fn delimiter_overlap_warning(starts: &[Vec<u8>], end: &[u8]) -> Option<String> {
+    if end.is_empty() { return None; }
+    let hex = |p: &[u8]| p.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+    starts.iter().filter(|s| !s.is_empty()).find_map(|start| {
+        let (s, e) = (hex(start), hex(end));
+        if start.as_slice() == end {
+            Some(format!("Start and end are both {}; every delimiter can open or close a frame", s))
+        } else if memmem::find(start, end).is_some() {
+            Some(format!("End {} appears inside start {}; frames may end right after they start", e, s))
+        } else if memmem::find(end, start).is_some() {
+            Some(format!("Start {} appears inside end {}; an end delimiter can be read as a new start", s, e))
+        } else if suffix_prefix_overlap(start, end) > 0 {
+            Some(format!("Start {} runs into end {}; overlapping bytes can end a frame early", s, e))
+        } else if suffix_prefix_overlap(end, start) > 0 {
+            Some(format!("End {} runs into start {}; overlapping bytes can hide the next start", e, s))
+        } else {
+            None
+        }
+    })
+}
+
+/// Cut complete messages from `buffer` using `mode`.
+pub fn frame_with_mode(buffer: &mut Vec<u8>, mode: FramingMode, starts: &[Vec<u8>], end: &[u8]) -> Vec<FramedMessage> {
+    match mode {
+        FramingMode::Delimited => frame_messages(buffer, starts, end),
+        FramingMode::StartDelimited => frame_start_delimited(buffer, starts),
+        FramingMode::LengthPrefixed(field) => frame_length_prefixed(buffer, starts, &field),
+    }
+}
+
+/// Empty `buffer`, returning whatever follows the first start delimiter as a `Flushed` frame.
+///
+/// Used when no more data will complete the frame (disconnect, end of an import). Bytes
+/// before any start delimiter, or a buffer with no start delimiter at all, are discarded.
+pub fn flush_partial(buffer: &mut Vec<u8>, starts: &[Vec<u8>]) -> Option<FramedMessage> {
+    let found = if starts.iter().any(|p| !p.is_empty()) {
+        find_first_start(buffer, starts).map(|(p, i)| (p, Some(i)))
+    } else {
+        Some((0, None))
+    };
+    let rest = std::mem::take(buffer);
+    let (s, start_pattern) = found?;
+    if s >= rest.len() { return None; }
+    Some(FramedMessage { bytes: rest[s..].to_vec(), skipped: s, start_pattern, status: FrameStatus::Flushed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `frame_messages` as it was before the memchr search, kept as the reference.
+    fn frame_messages_reference(buffer: &mut Vec<u8>, starts: &[Vec<u8>], end: &[u8]) -> Vec<FramedMessage> {
+        let find = |hay: &[u8], needle: &[u8]| hay.windows(needle.len()).position(|w| w == needle);
+        let mut messages = Vec::new();
+        let any_start = starts.iter().any(|p| !p.is_empty());
+        while !buffer.is_empty() {
+            let (s, start_pattern) = if !any_start {
+                (0, None)
+            } else {
+                let first = starts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| !p.is_empty())
+                    .filter_map(|(i, p)| find(buffer, p).map(|pos| (pos, i)))
+                    .min_by_key(|&(pos, i)| (pos, std::cmp::Reverse(starts[i].len())));
+                match first {
+                    Some((p, i)) => (p, Some(i)),
+                    None => break,
+                }
+            };
+            let after_start = s + start_pattern.map_or(0, |i| starts[i].len());
+            if after_start > buffer.len() { break; }
+            let end_pos = if end.is_empty() { Some(buffer.len()) } else { find(&buffer[after_start..], end).map(|p| after_start + p) };
+            let Some(e) = end_pos else { break };
+            let msg_end = e + end.len();
+            let status = if end.is_empty() { FrameStatus::NoEndDelimiter } else { FrameStatus::Clean };
+            messages.push(FramedMessage { bytes: buffer[s..msg_end].to_vec(), skipped: s, start_pattern, status });
+            buffer.drain(0..msg_end);
+        }
+        messages
+    }
+
+    /// xorshift64, so failures reproduce from the seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    type Frame = (Vec<u8>, usize, Option<usize>, FrameStatus);
+
+    fn parts(frames: Vec<FramedMessage>) -> Vec<Frame> {
+        frames.into_iter().map(|f| (f.bytes, f.skipped, f.start_pattern, f.status)).collect()
+    }
+
+    /// Frame `stream` in the given chunk sizes with both implementations.
+    fn run(stream: &[u8], chunks: &[usize], starts: &[Vec<u8>], end: &[u8]) -> [(Vec<Frame>, Vec<u8>); 2] {
+        let (mut fast, mut slow) = ((Vec::new(), Vec::new()), (Vec::new(), Vec::new()));
+        let mut rest = stream;
+        for &n in chunks {
+            let (chunk, tail) = rest.split_at(n.min(rest.len()));
+            rest = tail;
+            fast.1.extend_from_slice(chunk);
+            fast.0.extend(parts(frame_messages(&mut fast.1, starts, end)));
+            slow.1.extend_from_slice(chunk);
+            slow.0.extend(parts(frame_messages_reference(&mut slow.1, starts, end)));
+        }
+        [fast, slow]
+    }
+
+    #[test]
+    fn memchr_search_matches_reference() {
+        let pattern_sets: [(&[&[u8]], &[u8]); 6] = [
+            (&[&[0xAA]], &[0x0A]),
+            (&[&[0xAA, 0x55]], &[0x0D, 0x0A]),
+            (&[&[0xAA, 0x55], &[0x5A], &[0xAA]], &[0x0D, 0x0A]),
+            (&[&[0xAA, 0x55]], &[]),
+            (&[], &[0x0A]),
+            (&[&[0xAA, 0xAA]], &[0xAA, 0x0A]),
+        ];
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        // Few distinct bytes, so delimiters turn up often and overlap
+        let alphabet = [0xAA, 0x55, 0x5A, 0x0D, 0x0A, 0x00];
+        for (starts, end) in pattern_sets {
+            let starts: Vec<Vec<u8>> = starts.iter().map(|p| p.to_vec()).collect();
+            for _ in 0..200 {
+                let stream: Vec<u8> = (0..rng.below(300)).map(|_| alphabet[rng.below(alphabet.len())]).collect();
+                let mut chunks = Vec::new();
+                let mut total = 0;
+                while total < stream.len() {
+                    let n = 1 + rng.below(40);
+                    chunks.push(n);
+                    total += n;
+                }
+                let [fast, slow] = run(&stream, &chunks, &starts, end);
+                assert_eq!(fast, slow, "stream {:02X?} in chunks {:?}", stream, chunks);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Importing captured bytes from text files.
+use crate::app::state::parse_hex_bytes;
+
+/// Width of the offset column written by `hexdump -C` and the hex dump view.
+const OFFSET_DIGITS: usize = 8;
+
+/// Parse a text hex dump into raw bytes.
+///
+/// Accepts plain space-separated hex, `xxd` output (`00000010: 4865 6c6c  He..`) and
+/// `hexdump -C` / hex-dump-view output (`00000010  48 65 6c 6c  |He..|`). Offset columns
+/// and ASCII gutters are dropped; blank lines are skipped.
+pub fn parse_hex_dump(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut offset_rows = false;
+    for (line_no, line) in text.lines().enumerate() {
+        let mut body = line.trim();
+        // `hexdump -C` style gutter
+        let gutter = body.find('|');
+        if let Some(p) = gutter {
+            body = &body[..p];
+        }
+        // `xxd` style: offset ends in ':' and the ASCII gutter follows a double space
+        if let Some((offset, rest)) = body.split_once(':') {
+            if is_hex(offset.trim()) {
+                let rest = rest.trim_start();
+                body = rest.split_once("  ").map_or(rest, |(hex, _)| hex);
+            }
+        }
+        let mut tokens: Vec<&str> = body.split_whitespace().collect();
+        // Offset column without a colon: an 8-digit hex token leading single-byte tokens and a gutter
+        let is_offset = |t: &&str| t.len() == OFFSET_DIGITS && is_hex(t);
+        if gutter.is_some() && tokens.len() > 1 && is_offset(&tokens[0]) && tokens[1..].iter().all(|t| t.len() == 2 && is_hex(t)) {
+            tokens.remove(0);
+            offset_rows = true;
+        } else if offset_rows && tokens.len() == 1 && is_offset(&tokens[0]) {
+            // `hexdump -C` ends with the total length on a line of its own
+            continue;
+        }
+        let mut pairs = Vec::new();
+        for token in tokens {
+            let token = token.trim_start_matches("0x").trim_start_matches("0X");
+            if token.len() % 2 != 0 {
+                return Err(format!("line {}: odd number of hex digits in '{}'", line_no + 1, token));
+            }
+            // `xxd` groups several bytes per token
+            pairs.extend(token.as_bytes().chunks(2).map(|p| String::from_utf8_lossy(p).to_string()));
+        }
+        bytes.extend(parse_hex_bytes(&pairs.join(" ")).map_err(|e| format!("line {}: {}", line_no + 1, e))?);
+    }
+    Ok(bytes)
+}
+
+fn is_hex(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_hex_dump;
+
+    #[test]
+    fn plain_hex() {
+        assert_eq!(parse_hex_dump("AA 55 0x01\n\n02").unwrap(), vec![0xAA, 0x55, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn plain_hex_with_long_leading_token() {
+        assert_eq!(parse_hex_dump("AABBCCDD EE").unwrap(), vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE]);
+        assert_eq!(parse_hex_dump("AABBCCDD EEFF").unwrap(), vec![0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+    }
+
+    #[test]
+    fn xxd_output() {
+        let text = "00000000: 4865 6c6c 6f0a                           Hello.\n00000006: ff                                       .";
+        assert_eq!(parse_hex_dump(text).unwrap(), b"Hello\n\xff".to_vec());
+    }
+
+    #[test]
+    fn hexdump_c_output() {
+        let text = "00000000  48 65 6c 6c 6f 20 77 6f  72 6c 64 0a              |Hello world.|\n0000000c";
+        assert_eq!(parse_hex_dump(text).unwrap(), b"Hello world\n".to_vec());
+    }
+
+    #[test]
+    fn odd_digits_rejected() {
+        assert!(parse_hex_dump("AA B").is_err());
+    }
+}
@@ -1,8 +1,11 @@
 pub mod state;
 pub mod framing;
 pub mod net;
-pub mod ui;
 pub mod suspects;
-
-pub use ui::ByteBusterApp;
-
+pub mod export;
+pub mod recording;
+pub mod import;
+pub mod notify;
+pub mod checksum;
+pub mod engine;
+pub mod config;
@@ -1,40 +1,263 @@
-//! Networking layer: TCP connect and background IO threads.
-use crossbeam_channel::{bounded, select, Receiver, Sender};
-use log::error;
-use std::io::{Read, Write};
-use std::net::TcpStream;
+//! Networking layer: TCP connect, UDP sockets, serial ports, stdin capture and background IO threads.
+use crossbeam_channel::{bounded, select, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use log::{error, info};
+use std::io::{ErrorKind, Read, Write};
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
-/// Establish a TCP connection and spawn reader/writer threads.
+/// Default size of the reader thread's buffer, the most bytes delivered per read.
+pub const DEFAULT_READ_BUFFER: usize = 4096;
+/// Bounds for the read buffer size setting.
+pub const MIN_READ_BUFFER: usize = 1;
+pub const MAX_READ_BUFFER: usize = 1 << 20;
+/// Default cap on the reconnect backoff.
+pub const DEFAULT_MAX_BACKOFF_MS: usize = 30_000;
+
+/// Transport used to reach the address in the address bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+    /// The address is a serial port name such as `/dev/ttyUSB0` or `COM3`.
+    Serial,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Tcp => write!(f, "TCP"),
+            Transport::Udp => write!(f, "UDP"),
+            Transport::Serial => write!(f, "Serial"),
+        }
+    }
+}
+
+/// Channels and threads of a running link:
+/// `(tx_to_writer, rx_from_reader, link_events, shutdown, reader_join, writer_join)`.
+pub type LinkHandles = (Sender<Vec<u8>>, Receiver<Vec<u8>>, Receiver<LinkEvent>, LinkShutdown, thread::JoinHandle<()>, thread::JoinHandle<()>);
+
+/// Link tuning shared with the IO threads; changes apply to the running connection.
+#[derive(Debug)]
+pub struct LinkSettings {
+    /// Maximum outgoing bytes per second; 0 means unlimited.
+    pub send_bytes_per_sec: AtomicUsize,
+    /// Test aid: deliver received data one byte at a time with this delay (ms) between bytes; 0 is off.
+    pub read_delay_ms_per_byte: AtomicUsize,
+    /// Most bytes taken per read: small surfaces bytes sooner, large means fewer chunks.
+    pub read_buffer_size: AtomicUsize,
+    /// Re-dial the peer when the connection drops instead of ending the session.
+    pub auto_reconnect: AtomicBool,
+    /// Longest wait between reconnect attempts, in milliseconds.
+    pub max_backoff_ms: AtomicUsize,
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self {
+            send_bytes_per_sec: AtomicUsize::new(0),
+            read_delay_ms_per_byte: AtomicUsize::new(0),
+            read_buffer_size: AtomicUsize::new(DEFAULT_READ_BUFFER),
+            auto_reconnect: AtomicBool::new(false),
+            max_backoff_ms: AtomicUsize::new(DEFAULT_MAX_BACKOFF_MS),
+        }
+    }
+}
+
+/// Write `bytes`, pacing the writes so the rate stays under `settings.send_bytes_per_sec`.
+fn write_throttled(stream: &mut impl Write, bytes: &[u8], settings: &LinkSettings) -> std::io::Result<()> {
+    let limit = settings.send_bytes_per_sec.load(Ordering::Relaxed);
+    if limit == 0 {
+        return stream.write_all(bytes);
+    }
+    // Write in ~50ms slices so the link isn't idle for long stretches.
+    for slice in bytes.chunks((limit / 20).max(1)) {
+        stream.write_all(slice)?;
+        stream.flush()?;
+        thread::sleep(Duration::from_secs_f64(slice.len() as f64 / limit as f64));
+    }
+    Ok(())
+}
+
+/// Connect to `address` on a background thread so an unreachable host can't freeze the UI.
+///
+/// The returned channel yields the connected stream or an error message once. Dropping the
+/// receiver abandons the attempt; a stream that connects afterwards is closed.
+pub fn connect_in_background(address: String) -> Receiver<Result<TcpStream, String>> {
+    let (tx, rx) = bounded(1);
+    thread::spawn(move || {
+        let result = TcpStream::connect(address.as_str()).map_err(|e| format!("connect to {} failed: {}", address, e));
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+/// Spawn a thread that forwards everything read from stdin, for `cmd | byte_buster --stdin`.
 ///
-/// Returns `(tx_to_writer, rx_from_reader, reader_join, writer_join)`.
-pub fn spawn_connection(address: String) -> (Sender<Vec<u8>>, Receiver<Vec<u8>>, thread::JoinHandle<()>, thread::JoinHandle<()>) {
+/// Returns `(rx_from_reader, reader_join)`; the thread exits at end of input.
+pub fn spawn_stdin_reader() -> (Receiver<Vec<u8>>, thread::JoinHandle<()>) {
+    let (tx_from_reader, rx_from_reader) = bounded::<Vec<u8>>(1024);
+    let reader_handle = thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut stdin = std::io::stdin().lock();
+        loop {
+            match stdin.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx_from_reader.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    error!("stdin read error: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    (rx_from_reader, reader_handle)
+}
+
+/// Hand bytes read from a stream to the UI; `false` once the UI stopped listening.
+fn deliver(tx_from_reader: &Sender<Vec<u8>>, bytes: &[u8], settings: &LinkSettings) -> bool {
+    let delay = settings.read_delay_ms_per_byte.load(Ordering::Relaxed);
+    if delay == 0 {
+        return tx_from_reader.send(bytes.to_vec()).is_ok();
+    }
+    // Simulate a trickling link to exercise framing across chunk boundaries
+    for b in bytes {
+        thread::sleep(Duration::from_millis(delay as u64));
+        if tx_from_reader.send(vec![*b]).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Notices from the reader thread about the state of the link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// The peer went away; waiting `delay` before reconnect attempt `attempt`.
+    Reconnecting { attempt: u32, delay: Duration },
+    /// A reconnect succeeded; data flows again.
+    Reconnected,
+    /// The peer closed the connection and no reconnect will be tried.
+    Closed,
+}
+
+/// Wait before the first reconnect attempt; doubled after each failure up to `max_backoff_ms`.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+/// Give up on a single reconnect attempt after this long.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stops a connection's IO threads; see `LinkShutdown::shutdown`.
+pub struct LinkShutdown {
+    /// Never sent on: dropping it disconnects the threads' receiver, which is the signal.
+    signal: Option<Sender<()>>,
+    /// The live stream, replaced by the reader on reconnect.
+    stream: Arc<Mutex<Option<TcpStream>>>,
+}
+
+impl LinkShutdown {
+    /// Tell the reader and writer to exit and shut the socket down in both directions, so
+    /// a blocked read or write returns at once. The threads can then be joined.
+    pub fn shutdown(&mut self) {
+        self.signal.take();
+        if let Some(stream) = self.stream.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Whether `LinkShutdown::shutdown` has been called.
+fn shutdown_requested(signal: &Receiver<()>) -> bool {
+    matches!(signal.try_recv(), Err(TryRecvError::Disconnected))
+}
+
+/// Retry `addr` with exponential backoff until it connects.
+///
+/// Returns `None` if auto-reconnect is switched off meanwhile, shutdown is requested or
+/// the UI stopped listening.
+fn reconnect(addr: SocketAddr, settings: &LinkSettings, events: &Sender<LinkEvent>, signal: &Receiver<()>) -> Option<TcpStream> {
+    let mut delay = INITIAL_BACKOFF;
+    for attempt in 1.. {
+        if !settings.auto_reconnect.load(Ordering::Relaxed) { return None; }
+        events.send(LinkEvent::Reconnecting { attempt, delay }).ok()?;
+        // Sleep, waking early on shutdown
+        if signal.recv_timeout(delay) != Err(RecvTimeoutError::Timeout) { return None; }
+        match TcpStream::connect_timeout(&addr, RECONNECT_TIMEOUT) {
+            Ok(stream) => return Some(stream),
+            Err(e) => error!("reconnect to {} failed: {}", addr, e),
+        }
+        let max = Duration::from_millis(settings.max_backoff_ms.load(Ordering::Relaxed) as u64);
+        delay = (delay * 2).min(max.max(INITIAL_BACKOFF));
+    }
+    None
+}
+
+/// Spawn reader/writer threads for a connected stream.
+///
+/// With `settings.auto_reconnect` on, the reader re-dials the peer with backoff when the
+/// connection drops and hands the new stream to the writer, so the send channel and any
+/// queued sends survive. Progress is reported on the events channel.
+///
+pub fn spawn_connection(stream: TcpStream, settings: Arc<LinkSettings>) -> LinkHandles {
     let (tx_to_writer, rx_for_writer) = bounded::<Vec<u8>>(1024);
     let (tx_from_reader, rx_from_reader) = bounded::<Vec<u8>>(1024);
-    let stream = TcpStream::connect(address.clone()).expect("failed to connect");
+    let (tx_events, rx_events) = bounded::<LinkEvent>(16);
+    // Replacement streams for the writer after a reconnect
+    let (tx_new_stream, rx_new_stream) = bounded::<TcpStream>(1);
+    let (tx_signal, rx_signal) = bounded::<()>(0);
+    let live_stream = Arc::new(Mutex::new(stream.try_clone().ok()));
+    let shutdown = LinkShutdown { signal: Some(tx_signal), stream: Arc::clone(&live_stream) };
+    let signal_writer = rx_signal.clone();
     stream
         .set_read_timeout(Some(Duration::from_millis(200)))
         .ok();
+    let peer = stream.peer_addr().ok();
     let stream_reader = stream.try_clone().expect("clone stream failed");
     let stream_writer = stream;
 
+    let settings_reader = Arc::clone(&settings);
     let reader_handle = thread::spawn(move || {
-        let mut buf = [0u8; 4096];
+        let mut buf = Vec::new();
         let mut local_stream = stream_reader;
-        loop {
-            match local_stream.read(&mut buf) {
-                Ok(0) => {
-                    break;
-                }
+        // The read timeout bounds how long a shutdown goes unnoticed
+        while !shutdown_requested(&rx_signal) {
+            let size = settings_reader.read_buffer_size.load(Ordering::Relaxed).clamp(MIN_READ_BUFFER, MAX_READ_BUFFER);
+            buf.resize(size, 0);
+            let dropped = match local_stream.read(&mut buf) {
+                Ok(0) => true,
                 Ok(n) => {
-                    let chunk = buf[..n].to_vec();
-                    if tx_from_reader.send(chunk).is_err() {
-                        break;
-                    }
+                    if !deliver(&tx_from_reader, &buf[..n], &settings_reader) { break; }
+                    false
                 }
-                Err(_e) => {}
-            }
+                // Read timeout: check the channels again
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => false,
+                Err(e) => {
+                    error!("read error: {}", e);
+                    true
+                }
+            };
+            if !dropped { continue; }
+            if shutdown_requested(&rx_signal) { break; }
+            let Some(stream) = peer.and_then(|addr| reconnect(addr, &settings_reader, &tx_events, &rx_signal)) else {
+                if !shutdown_requested(&rx_signal) {
+                    let _ = tx_events.send(LinkEvent::Closed);
+                }
+                break;
+            };
+            stream.set_read_timeout(Some(Duration::from_millis(200))).ok();
+            let Ok(writer_stream) = stream.try_clone() else { break };
+            if tx_new_stream.send(writer_stream).is_err() { break; }
+            *live_stream.lock().unwrap_or_else(|e| e.into_inner()) = stream.try_clone().ok();
+            local_stream = stream;
+            info!("reconnected");
+            let _ = tx_events.send(LinkEvent::Reconnected);
         }
     });
 
@@ -42,12 +265,31 @@ pub fn spawn_connection(address: String) -> (Sender<Vec<u8>>, Receiver<Vec<u8>>,
         let mut local_stream = stream_writer;
         loop {
             select! {
+                recv(signal_writer) -> _ => break,
+                recv(rx_new_stream) -> stream => {
+                    match stream {
+                        Ok(stream) => local_stream = stream,
+                        Err(_) => break,
+                    }
+                }
                 recv(rx_for_writer) -> msg => {
                     match msg {
                         Ok(bytes) => {
-                            if let Err(e) = local_stream.write_all(&bytes) {
+                            // A reconnect may have replaced the stream since the last select
+                            while let Ok(stream) = rx_new_stream.try_recv() {
+                                local_stream = stream;
+                            }
+                            while let Err(e) = write_throttled(&mut local_stream, &bytes, &settings) {
                                 error!("write error: {}", e);
-                                break;
+                                if !settings.auto_reconnect.load(Ordering::Relaxed) { return; }
+                                // Hold the bytes until the reader has a new stream
+                                select! {
+                                    recv(signal_writer) -> _ => return,
+                                    recv(rx_new_stream) -> stream => match stream {
+                                        Ok(stream) => local_stream = stream,
+                                        Err(_) => return,
+                                    },
+                                }
                             }
                         }
                         Err(_) => break,
@@ -58,6 +300,178 @@ pub fn spawn_connection(address: String) -> (Sender<Vec<u8>>, Receiver<Vec<u8>>,
         }
     });
 
-    (tx_to_writer, rx_from_reader, reader_handle, writer_handle)
+    (tx_to_writer, rx_from_reader, rx_events, shutdown, reader_handle, writer_handle)
 }
 
+/// Largest UDP payload; the reader's buffer must hold a whole datagram.
+const MAX_DATAGRAM: usize = 65_535;
+
+/// Open a UDP socket on `bind_or_connect` and spawn reader/writer threads for it.
+///
+/// An address with an unspecified IP (`0.0.0.0:9000`, `[::]:9000`, or just `:9000`) binds
+/// that local port and listens to any sender; sends go to whoever sent last. Any other
+/// address is the peer: an ephemeral local port is bound and connected to it.
+///
+/// Each datagram reaches `rx_from_reader` as one chunk and each queued send goes out as
+/// one datagram. UDP has no connection to lose, so there is nothing to reconnect.
+pub fn spawn_udp_connection(bind_or_connect: String, settings: Arc<LinkSettings>) -> Result<LinkHandles, String> {
+    let address = bind_or_connect.trim();
+    let address = if address.starts_with(':') { format!("0.0.0.0{}", address) } else { address.to_string() };
+    let target = address
+        .to_socket_addrs()
+        .map_err(|e| format!("resolve {} failed: {}", address, e))?
+        .next()
+        .ok_or_else(|| format!("{} did not resolve to an address", address))?;
+    let listening = target.ip().is_unspecified();
+    let socket = if listening {
+        UdpSocket::bind(target).map_err(|e| format!("bind {} failed: {}", target, e))?
+    } else {
+        let local: SocketAddr = if target.is_ipv4() { (Ipv4Addr::UNSPECIFIED, 0).into() } else { (Ipv6Addr::UNSPECIFIED, 0).into() };
+        let socket = UdpSocket::bind(local).map_err(|e| format!("bind {} failed: {}", local, e))?;
+        socket.connect(target).map_err(|e| format!("connect to {} failed: {}", target, e))?;
+        socket
+    };
+    socket.set_read_timeout(Some(Duration::from_millis(200))).ok();
+    let socket_writer = socket.try_clone().map_err(|e| format!("clone socket failed: {}", e))?;
+    info!("UDP socket on {}", socket.local_addr().map_or_else(|_| address.clone(), |a| a.to_string()));
+
+    let (tx_to_writer, rx_for_writer) = bounded::<Vec<u8>>(1024);
+    let (tx_from_reader, rx_from_reader) = bounded::<Vec<u8>>(1024);
+    let (tx_events, rx_events) = bounded::<LinkEvent>(16);
+    let (tx_signal, rx_signal) = bounded::<()>(0);
+    // Nothing to shut down on a UDP socket; the read timeout bounds the reader's exit
+    let shutdown = LinkShutdown { signal: Some(tx_signal), stream: Arc::new(Mutex::new(None)) };
+    let signal_writer = rx_signal.clone();
+    // Where sends go: the peer, or in listen mode the latest sender
+    let last_peer = Arc::new(Mutex::new((!listening).then_some(target)));
+    let last_peer_writer = Arc::clone(&last_peer);
+
+    let reader_handle = thread::spawn(move || {
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        while !shutdown_requested(&rx_signal) {
+            match socket.recv_from(&mut buf) {
+                Ok((n, from)) => {
+                    if listening {
+                        *last_peer.lock().unwrap_or_else(|e| e.into_inner()) = Some(from);
+                    }
+                    if tx_from_reader.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                // ICMP port unreachable from an earlier send; the peer may come up later
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => error!("UDP peer unreachable: {}", e),
+                Err(e) => {
+                    error!("UDP read error: {}", e);
+                    let _ = tx_events.send(LinkEvent::Closed);
+                    break;
+                }
+            }
+        }
+    });
+
+    let writer_handle = thread::spawn(move || {
+        loop {
+            select! {
+                recv(signal_writer) -> _ => break,
+                recv(rx_for_writer) -> msg => {
+                    let Ok(bytes) = msg else { break };
+                    let peer = *last_peer_writer.lock().unwrap_or_else(|e| e.into_inner());
+                    let result = match peer {
+                        Some(_) if !listening => socket_writer.send(&bytes),
+                        Some(peer) => socket_writer.send_to(&bytes, peer),
+                        None => {
+                            error!("UDP send dropped: nothing received yet, so there is no peer to reply to");
+                            continue;
+                        }
+                    };
+                    if let Err(e) = result {
+                        error!("UDP write error: {}", e);
+                    }
+                    // A datagram can't be split, so pace whole datagrams
+                    let limit = settings.send_bytes_per_sec.load(Ordering::Relaxed);
+                    if limit > 0 {
+                        thread::sleep(Duration::from_secs_f64(bytes.len() as f64 / limit as f64));
+                    }
+                }
+            }
+        }
+    });
+
+    Ok((tx_to_writer, rx_from_reader, rx_events, shutdown, reader_handle, writer_handle))
+}
+
+/// Common serial baud rates offered in the address bar.
+pub const SERIAL_BAUD_RATES: [u32; 9] = [9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600, 1_000_000];
+/// Baud rate a new connection starts with.
+pub const DEFAULT_SERIAL_BAUD: u32 = 115200;
+
+/// Names of the serial ports present on the system, or none if they can't be listed.
+pub fn available_serial_ports() -> Vec<String> {
+    match serialport::available_ports() {
+        Ok(ports) => ports.into_iter().map(|p| p.port_name).collect(),
+        Err(e) => {
+            error!("listing serial ports failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Open serial port `port` at `baud` (8N1, no flow control) and spawn reader/writer threads.
+///
+/// Each read delivers whatever bytes have arrived as one chunk and framing does the rest.
+/// Unplugging the device ends the session with `LinkEvent::Closed`.
+pub fn spawn_serial_connection(port: String, baud: u32, settings: Arc<LinkSettings>) -> Result<LinkHandles, String> {
+    let port_reader = serialport::new(port.as_str(), baud)
+        .timeout(Duration::from_millis(200))
+        .open()
+        .map_err(|e| format!("open {} failed: {}", port, e))?;
+    let mut port_writer = port_reader.try_clone().map_err(|e| format!("clone {} failed: {}", port, e))?;
+    info!("opened {} at {} baud", port, baud);
+
+    let (tx_to_writer, rx_for_writer) = bounded::<Vec<u8>>(1024);
+    let (tx_from_reader, rx_from_reader) = bounded::<Vec<u8>>(1024);
+    let (tx_events, rx_events) = bounded::<LinkEvent>(16);
+    let (tx_signal, rx_signal) = bounded::<()>(0);
+    // The read timeout bounds how long the reader takes to notice a shutdown
+    let shutdown = LinkShutdown { signal: Some(tx_signal), stream: Arc::new(Mutex::new(None)) };
+    let signal_writer = rx_signal.clone();
+
+    let settings_reader = Arc::clone(&settings);
+    let reader_handle = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let mut local_port = port_reader;
+        while !shutdown_requested(&rx_signal) {
+            let size = settings_reader.read_buffer_size.load(Ordering::Relaxed).clamp(MIN_READ_BUFFER, MAX_READ_BUFFER);
+            buf.resize(size, 0);
+            match local_port.read(&mut buf) {
+                Ok(0) => {}
+                Ok(n) => {
+                    if !deliver(&tx_from_reader, &buf[..n], &settings_reader) { break; }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                Err(e) => {
+                    error!("{} read error: {}", port, e);
+                    let _ = tx_events.send(LinkEvent::Closed);
+                    break;
+                }
+            }
+        }
+    });
+
+    let writer_handle = thread::spawn(move || {
+        loop {
+            select! {
+                recv(signal_writer) -> _ => break,
+                recv(rx_for_writer) -> msg => {
+                    let Ok(bytes) = msg else { break };
+                    if let Err(e) = write_throttled(&mut port_writer, &bytes, &settings) {
+                        error!("serial write error: {}", e);
+                    }
+                }
+            }
+        }
+    });
+
+    Ok((tx_to_writer, rx_from_reader, rx_events, shutdown, reader_handle, writer_handle))
+}
@@ -0,0 +1,43 @@
+//! Desktop notifications for critical suspect violations.
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::error;
+
+/// Minimum time between two desktop notifications.
+pub const NOTIFY_MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Rate-limits critical alerts sent to the OS notification service.
+#[derive(Default)]
+pub struct CriticalNotifier {
+    last_sent: Option<Instant>,
+    /// Alerts dropped by the rate limit since the last notification.
+    suppressed: usize,
+}
+
+impl CriticalNotifier {
+    /// Show `text` as a desktop notification unless one was sent within `NOTIFY_MIN_INTERVAL`.
+    pub fn notify(&mut self, text: &str) {
+        if self.last_sent.is_some_and(|t| t.elapsed() < NOTIFY_MIN_INTERVAL) {
+            self.suppressed += 1;
+            return;
+        }
+        let body = if self.suppressed > 0 {
+            format!("{}\n(+{} more since the last alert)", text, self.suppressed)
+        } else {
+            text.to_string()
+        };
+        self.last_sent = Some(Instant::now());
+        self.suppressed = 0;
+        // Talking to the notification daemon can block, so keep it off the UI thread.
+        thread::spawn(move || {
+            if let Err(e) = notify_rust::Notification::new()
+                .summary("ByteBuster: critical violation")
+                .body(&body)
+                .show()
+            {
+                error!("desktop notification failed: {}", e);
+            }
+        });
+    }
+}
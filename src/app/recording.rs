@@ -0,0 +1,148 @@
+//! Raw session recording and replay.
+//!
+//! A recording is the `SESSION_MAGIC` header followed by one record per received chunk:
+//! the offset from the start of the recording in microseconds (u64 LE), the chunk
+//! length (u32 LE), then the chunk bytes.
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Header identifying a ByteBuster session recording.
+pub const SESSION_MAGIC: &[u8; 8] = b"BBSESS01";
+
+/// Appends every received chunk to a recording file with its arrival time.
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    /// File name shown in the UI.
+    pub name: String,
+    /// Chunks written so far.
+    pub chunks: usize,
+    /// Payload bytes written so far.
+    pub bytes: usize,
+}
+
+impl SessionRecorder {
+    /// Create (or truncate) `path` and write the header.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(SESSION_MAGIC)?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            chunks: 0,
+            bytes: 0,
+        })
+    }
+
+    /// Append one chunk stamped with the time since recording started.
+    pub fn record(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let offset = self.started.elapsed().as_micros() as u64;
+        self.writer.write_all(&offset.to_le_bytes())?;
+        self.writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        self.writer.write_all(chunk)?;
+        self.chunks += 1;
+        self.bytes += chunk.len();
+        Ok(())
+    }
+
+    /// Flush buffered records to disk.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Read the records that follow the header, stopping at a truncated trailing record
+/// (e.g. from a recording cut short by a crash).
+pub fn read_records(mut reader: impl Read) -> io::Result<Vec<(Duration, Vec<u8>)>> {
+    let mut chunks = Vec::new();
+    let mut header = [0u8; 12];
+    loop {
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let offset = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let len = u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+        // The length comes from the file, so read what is there rather than allocating it up front
+        let mut data = Vec::new();
+        (&mut reader).take(len as u64).read_to_end(&mut data)?;
+        if data.len() < len { break; }
+        chunks.push((Duration::from_micros(offset), data));
+    }
+    Ok(chunks)
+}
+
+/// Feeds the chunks of a recording back on their original schedule.
+pub struct SessionReplay {
+    chunks: Vec<(Duration, Vec<u8>)>,
+    next: usize,
+    started: Instant,
+    /// File name shown in the UI.
+    pub name: String,
+}
+
+impl SessionReplay {
+    /// Load a whole recording into memory; replay starts immediately.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SESSION_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a ByteBuster session recording"));
+        }
+        let chunks = read_records(reader)?;
+        Ok(Self {
+            chunks,
+            next: 0,
+            started: Instant::now(),
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        })
+    }
+
+    /// Take every chunk whose original arrival time has passed.
+    pub fn due_chunks(&mut self) -> Vec<Vec<u8>> {
+        let elapsed = self.started.elapsed();
+        let mut due = Vec::new();
+        while let Some((offset, data)) = self.chunks.get_mut(self.next) {
+            if *offset > elapsed { break; }
+            due.push(std::mem::take(data));
+            self.next += 1;
+        }
+        due
+    }
+
+    /// Time until the next chunk is due, or `None` once everything has been replayed.
+    pub fn time_to_next(&self) -> Option<Duration> {
+        self.chunks.get(self.next).map(|(offset, _)| offset.saturating_sub(self.started.elapsed()))
+    }
+
+    /// Chunks replayed so far and the total in the recording.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.chunks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_records;
+    use std::time::Duration;
+
+    fn record(offset: u64, chunk: &[u8], len: u32) -> Vec<u8> {
+        let mut out = offset.to_le_bytes().to_vec();
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(chunk);
+        out
+    }
+
+    #[test]
+    fn stops_at_truncated_record() {
+        let mut data = record(5, b"abc", 3);
+        data.extend(record(9, b"de", u32::MAX));
+        let chunks = read_records(data.as_slice()).unwrap();
+        assert_eq!(chunks, vec![(Duration::from_micros(5), b"abc".to_vec())]);
+    }
+}
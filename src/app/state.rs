@@ -2,11 +2,20 @@
 //!
 //! This module defines the shared types used across the GUI, networking,
 //! and framing layers, along with parsing/formatting helpers.
-use crossbeam_channel::{Receiver, Sender};
+use chrono::{DateTime, Local};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::app::checksum::ChecksumAlgo;
+use crate::app::engine::{EngineState, FramedResult};
+use crate::app::framing::{delimiter_overlap_warning, FrameStatus, FramedMessage};
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// How to render watched bytes.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WatchView {
     /// Render as hexadecimal (e.g. `0A FF`).
     Hex,
@@ -14,6 +23,15 @@ pub enum WatchView {
     Text,
     /// Render as space-separated binary octets (e.g. `00001010`).
     Binary,
+    /// Render the checksum of the watched bytes in hex.
+    Checksum(ChecksumAlgo),
+    /// Decode as an unsigned integer and show its name from the watch's `enum_names`.
+    Enum,
+    /// Read each byte as a signed two's-complement value (e.g. `-3, 127`).
+    SignedByte,
+    /// Decode the whole slice as one `width`-byte integer in the watch's byte order and
+    /// show it in decimal; `-` if the slice isn't exactly `width` bytes.
+    Integer { width: usize, signed: bool },
 }
 
 impl fmt::Display for WatchView {
@@ -22,17 +40,41 @@ impl fmt::Display for WatchView {
             WatchView::Hex => write!(f, "Hex"),
             WatchView::Text => write!(f, "Text"),
             WatchView::Binary => write!(f, "Binary"),
+            WatchView::Checksum(algo) => write!(f, "Checksum ({})", algo),
+            WatchView::Enum => write!(f, "Enum"),
+            WatchView::SignedByte => write!(f, "Signed bytes"),
+            WatchView::Integer { width, signed } => write!(f, "{}{}", if *signed { "I" } else { "U" }, width * 8),
+        }
+    }
+}
+
+/// Byte order used when grouping or decoding multi-byte values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Endian {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first (stream order).
+    Big,
+}
+
+impl fmt::Display for Endian {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endian::Little => write!(f, "LE"),
+            Endian::Big => write!(f, "BE"),
         }
     }
 }
 
 /// Where a watch should apply.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WatchTarget {
     /// Apply to all messages.
     All,
     /// Apply only when a message matches the given label.
     Label(String),
+    /// Apply to every `n`th message of the received sequence, starting at index `offset`.
+    EveryNth { n: u64, offset: u64 },
 }
 
 impl fmt::Display for WatchTarget {
@@ -40,12 +82,24 @@ impl fmt::Display for WatchTarget {
         match self {
             WatchTarget::All => write!(f, "All messages"),
             WatchTarget::Label(name) => write!(f, "{}", name),
+            WatchTarget::EveryNth { n, offset } => write!(f, "Every {} from #{}", n, offset),
         }
     }
 }
 
+/// Whether a watch or suspect `target` applies to the message at sequence position `index`
+/// with `active_label`.
+pub fn target_applies(target: &WatchTarget, active_label: &Option<String>, index: u64) -> bool {
+    match (target, active_label) {
+        (WatchTarget::All, _) => true,
+        (WatchTarget::EveryNth { n, offset }, _) => index >= *offset && (index - offset).is_multiple_of((*n).max(1)),
+        (WatchTarget::Label(name), Some(lbl)) => name == lbl,
+        (WatchTarget::Label(_), None) => false,
+    }
+}
+
 /// A configured item to watch in each message.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WatchItem {
     /// Display name.
     pub name: String,
@@ -55,24 +109,285 @@ pub struct WatchItem {
     pub end_index: usize,
     /// Rendering preference.
     pub view: WatchView,
+    /// Byte order within each `unit_size` group.
+    pub endian: Endian,
     /// Which messages this watch applies to.
     pub target: WatchTarget,
+    /// Value names used by `WatchView::Enum`.
+    pub enum_names: Vec<(u64, String)>,
 }
 
-/// A rule that assigns a human-friendly label to a message
-/// when a slice of its bytes equals the expected value.
-#[derive(Clone, Debug)]
-pub struct LabelRule {
-    /// Label to display when the rule matches.
-    pub name: String,
+impl WatchItem {
+    /// Render the watched bytes according to this item's view.
+    pub fn format_value(&self, bytes: &[u8], unit_size: usize, uppercase: bool) -> String {
+        match self.view {
+            WatchView::Enum => {
+                let name = decode_unsigned(bytes, self.endian)
+                    .and_then(|value| self.enum_names.iter().find(|(v, _)| *v == value));
+                match name {
+                    Some((_, name)) => name.clone(),
+                    None => format_bytes_for_view(bytes, WatchView::Enum, unit_size, self.endian, uppercase),
+                }
+            }
+            view => format_bytes_for_view(bytes, view, unit_size, self.endian, uppercase),
+        }
+    }
+
+    /// The plain number behind a numeric view (enum value or checksum), if the view has one.
+    pub fn raw_number(&self, bytes: &[u8]) -> Option<u64> {
+        match self.view {
+            WatchView::Enum => decode_unsigned(bytes, self.endian),
+            WatchView::Checksum(algo) => Some(algo.compute(bytes) as u64),
+            _ => None,
+        }
+    }
+
+    /// The number the view displays, for statistics: integers of the configured width, enum
+    /// values and a single signed byte. Text, hex, binary and checksum views have none.
+    pub fn numeric_value(&self, bytes: &[u8]) -> Option<f64> {
+        match self.view {
+            WatchView::Integer { width, .. } if bytes.len() != width => None,
+            WatchView::Integer { signed: true, .. } => decode_signed(bytes, self.endian).map(|v| v as f64),
+            WatchView::Integer { signed: false, .. } | WatchView::Enum => decode_unsigned(bytes, self.endian).map(|v| v as f64),
+            WatchView::SignedByte => match bytes {
+                [b] => Some(*b as i8 as f64),
+                _ => None,
+            },
+            WatchView::Hex | WatchView::Text | WatchView::Binary | WatchView::Checksum(_) => None,
+        }
+    }
+}
+
+/// Parse enum value names such as `1=PING, 2=REQUEST, 0x10=ERROR`.
+pub fn parse_enum_names(input: &str) -> Result<Vec<(u64, String)>, String> {
+    let mut names = Vec::new();
+    for entry in input.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() { continue; }
+        let (value, name) = entry.split_once('=').ok_or_else(|| format!("expected value=name, got '{}'", entry))?;
+        let value = value.trim();
+        let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => value.parse::<u64>(),
+        };
+        let value = parsed.map_err(|_| format!("invalid enum value '{}'", value))?;
+        names.push((value, name.trim().to_string()));
+    }
+    Ok(names)
+}
+
+/// Format enum value names the way `parse_enum_names` reads them back.
+pub fn format_enum_names(names: &[(u64, String)]) -> String {
+    names.iter().map(|(v, n)| format!("{}={}", v, n)).collect::<Vec<_>>().join(", ")
+}
+
+/// Running statistics of a watch's numeric value over the session.
+#[derive(Clone, Debug, Default)]
+pub struct WatchStats {
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+}
+
+impl WatchStats {
+    /// Fold one sample into the accumulators.
+    pub fn record(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    /// Mean of all samples, or 0 if there are none.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.sum / self.count as f64 }
+    }
+}
+
+/// Label name used in per-label statistics for messages no rule matched.
+pub const UNLABELED: &str = "Unlabeled";
+/// Span over which per-label message rates are measured.
+pub const LABEL_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Message count and recent arrival times for one label.
+#[derive(Clone, Debug, Default)]
+pub struct LabelStats {
+    pub count: u64,
+    /// Arrival times within the last `LABEL_RATE_WINDOW`, oldest first.
+    recent: VecDeque<Instant>,
+}
+
+impl LabelStats {
+    /// Count one message arriving now.
+    pub fn record(&mut self, now: Instant) {
+        self.count += 1;
+        self.recent.push_back(now);
+        self.expire(now);
+    }
+
+    /// Messages per second over the last `LABEL_RATE_WINDOW`.
+    pub fn rate(&mut self, now: Instant) -> f64 {
+        self.expire(now);
+        self.recent.len() as f64 / LABEL_RATE_WINDOW.as_secs_f64()
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) > LABEL_RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// How a label rule compares its slice of the message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelMatchMode {
+    /// The slice must equal the expected bytes.
+    Exact,
+    /// The slice, read as UTF-8 text, must match a regex.
+    Regex,
+}
+
+impl fmt::Display for LabelMatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelMatchMode::Exact => write!(f, "Exact bytes"),
+            LabelMatchMode::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+/// End index meaning "up to the last byte of the message", as parsed from `10..`.
+pub const WHOLE_MESSAGE_END: usize = usize::MAX;
+
+/// One byte-range test within a label rule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelCondition {
     /// Start index (inclusive).
     pub start_index: usize,
-    /// End index (inclusive).
+    /// End index (inclusive); `WHOLE_MESSAGE_END` runs to the end of the message.
     pub end_index: usize,
     /// Expected byte value for the slice.
     pub value: Vec<u8>,
+    /// Per-byte mask applied to both slice and value before comparing; empty means all `0xFF`.
+    pub mask: Vec<u8>,
+    /// How the slice is compared.
+    pub mode: LabelMatchMode,
+    /// Regex source used in `Regex` mode.
+    pub pattern: String,
+    /// Compiled `pattern`, cached so it isn't rebuilt for every message.
+    #[serde(skip)]
+    pub regex: Option<Regex>,
+}
+
+/// A rule that assigns a human-friendly label to a message
+/// when all of its conditions match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelRule {
+    /// Label to display when the rule matches.
+    pub name: String,
+    /// Conditions that must all match; most rules have exactly one.
+    pub conditions: Vec<LabelCondition>,
+    /// RGB color used to tint matching message cards.
+    pub color: [u8; 3],
+}
+
+/// Default tint for new label rules.
+pub const DEFAULT_LABEL_COLOR: [u8; 3] = [90, 150, 230];
+
+/// Editable text inputs for one label condition row.
+#[derive(Clone, Debug)]
+pub struct LabelConditionForm {
+    pub range: String,
+    pub value_hex: String,
+    pub mask_hex: String,
+    pub mode: LabelMatchMode,
+    pub pattern: String,
+}
+
+impl Default for LabelConditionForm {
+    fn default() -> Self {
+        Self {
+            range: String::new(),
+            value_hex: String::new(),
+            mask_hex: String::new(),
+            mode: LabelMatchMode::Exact,
+            pattern: String::new(),
+        }
+    }
+}
+
+impl From<&LabelCondition> for LabelConditionForm {
+    fn from(c: &LabelCondition) -> Self {
+        Self {
+            range: format_label_range(c),
+            value_hex: format_hex_input(&c.value),
+            mask_hex: format_hex_input(&c.mask),
+            mode: c.mode,
+            pattern: c.pattern.clone(),
+        }
+    }
+}
+
+/// How message bodies are rendered in the central panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// Hex bytes on a single line.
+    Hex,
+    /// UTF-8 text (lossy for invalid sequences).
+    Text,
+    /// Offset, hex, and ASCII columns like a hex editor.
+    HexDump,
+    /// Text with control bytes drawn as Unicode control pictures (`␍␊`).
+    ControlPictures,
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayMode::Hex => write!(f, "Hex"),
+            DisplayMode::Text => write!(f, "Text"),
+            DisplayMode::HexDump => write!(f, "Hexdump"),
+            DisplayMode::ControlPictures => write!(f, "Control chars"),
+        }
+    }
+}
+
+/// Base color theme for the UI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
 }
 
+/// Allowed range for the UI zoom factor.
+pub const MIN_ZOOM: f32 = 0.75;
+pub const MAX_ZOOM: f32 = 3.0;
+
+/// Allowed range for `max_messages`.
+pub const MIN_MAX_MESSAGES: usize = 10;
+pub const MAX_MAX_MESSAGES: usize = 100_000;
+
+/// An in-progress "Send file" transfer, paced chunk by chunk from the UI loop.
+pub struct FileTransfer {
+    /// File name shown in the progress indicator.
+    pub name: String,
+    /// Entire file contents.
+    pub data: Vec<u8>,
+    /// Number of bytes already queued to the writer.
+    pub sent: usize,
+    /// When the previous chunk was queued.
+    pub last_chunk: Instant,
+}
+
+/// Maximum number of entries kept in the send history.
+pub const MAX_SEND_HISTORY: usize = 50;
+
 /// Tabs for the left-hand configuration panel.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LeftPanelTab {
@@ -81,34 +396,318 @@ pub enum LeftPanelTab {
     Suspects,
 }
 
+/// A framed message as stored in the message list.
+#[derive(Clone, Debug)]
+pub struct ReceivedMessage {
+    pub bytes: Vec<u8>,
+    /// Index into `start_bytes` of the delimiter that framed this message.
+    pub start_pattern: Option<usize>,
+    /// Number of identical frames collapsed into this entry (1 if none).
+    pub repeat: usize,
+    /// Pinned messages are never dropped by the `max_messages` cap.
+    pub pinned: bool,
+    pub status: FrameStatus,
+    /// Position of this frame in the received sequence, counted from 0.
+    pub index: u64,
+    /// CRC-32 of `bytes`, used to spot identical frames at a glance.
+    pub fingerprint: u32,
+    /// Name of the first matching label rule, cached when the message is stored.
+    pub label: Option<String>,
+    /// Suspect rule warnings, with the severity in force when the message arrived.
+    pub warnings: Vec<(crate::app::suspects::Severity, String)>,
+    /// Freeform annotation shown under the card and exported with the message.
+    pub note: String,
+    /// Height of the message's card when last drawn, for placing rows in the list; 0 until drawn.
+    pub card_height: f32,
+    /// When framing completed; for a collapsed entry, the first of its repeats.
+    pub received_at: Instant,
+    /// `received_at` on the wall clock, for display and export.
+    pub wall_clock: DateTime<Local>,
+}
+
+impl ReceivedMessage {
+    /// Whether any of the message's warnings is Critical.
+    pub fn is_critical(&self) -> bool {
+        self.warnings.iter().any(|(sev, _)| *sev == crate::app::suspects::Severity::Critical)
+    }
+
+    /// The stored frame, for evaluating it again.
+    pub fn framed(&self) -> FramedMessage {
+        FramedMessage { bytes: self.bytes.clone(), skipped: 0, start_pattern: self.start_pattern, status: self.status }
+    }
+}
+
+/// Which earlier messages a new frame is compared against when collapsing duplicates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupScope {
+    /// Only the most recent message.
+    Consecutive,
+    /// Any of the last `DEDUP_WINDOW` messages.
+    Recent,
+}
+
+impl fmt::Display for DedupScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DedupScope::Consecutive => write!(f, "Consecutive only"),
+            DedupScope::Recent => write!(f, "Any recent"),
+        }
+    }
+}
+
+/// What the message filter box is compared against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    /// The message contains the query's bytes, written as hex (`0A FF` or `0AFF`).
+    HexContains,
+    /// The message, read as text, contains the query, ignoring ASCII case.
+    TextContains,
+    /// The message's label is exactly the query.
+    LabelEquals,
+}
+
+impl fmt::Display for FilterMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterMode::HexContains => write!(f, "Hex contains"),
+            FilterMode::TextContains => write!(f, "Text contains"),
+            FilterMode::LabelEquals => write!(f, "Label equals"),
+        }
+    }
+}
+
+/// Whether a message passes the filter box; an empty query matches everything and
+/// hex that doesn't parse matches nothing.
+pub fn message_matches_filter(msg: &[u8], label: &Option<String>, query: &str, mode: FilterMode) -> bool {
+    let query = query.trim();
+    if query.is_empty() { return true; }
+    match mode {
+        FilterMode::HexContains => {
+            let digits: String = query
+                .split_whitespace()
+                .map(|t| t.trim_start_matches("0x").trim_start_matches("0X"))
+                .collect();
+            match hex::decode(digits) {
+                Ok(needle) => memchr::memmem::find(msg, &needle).is_some(),
+                Err(_) => false,
+            }
+        }
+        FilterMode::TextContains => String::from_utf8_lossy(msg).to_ascii_lowercase().contains(&query.to_ascii_lowercase()),
+        FilterMode::LabelEquals => label.as_deref() == Some(query),
+    }
+}
+
+/// How many recent messages `DedupScope::Recent` searches for a duplicate.
+pub const DEDUP_WINDOW: usize = 32;
+
+/// The per-connection part of `AppState` for a tab that isn't selected.
+///
+/// The selected tab's connection lives in the matching `AppState` fields; `swap_connection`
+/// exchanges the two when switching tabs. Rules, watches and display settings are shared.
+pub struct Connection {
+    pub address_input: String,
+    pub transport: crate::app::net::Transport,
+    pub serial_baud: u32,
+    pub is_connected: bool,
+    pub connecting: Option<Receiver<Result<std::net::TcpStream, String>>>,
+    pub connect_error: Option<String>,
+    pub peer_addr: Option<std::net::SocketAddr>,
+    pub connected_since: Option<Instant>,
+    pub tx_to_writer: Option<Sender<Vec<u8>>>,
+    pub rx_from_reader: Option<Receiver<Vec<u8>>>,
+    pub link_events: Option<Receiver<crate::app::net::LinkEvent>>,
+    pub reconnecting: Option<u32>,
+    pub reader_join: Option<std::thread::JoinHandle<()>>,
+    pub writer_join: Option<std::thread::JoinHandle<()>>,
+    pub link_shutdown: Option<crate::app::net::LinkShutdown>,
+    pub incoming_buffer: Vec<u8>,
+    pub last_chunk_at: Option<Instant>,
+    pub received_messages: VecDeque<ReceivedMessage>,
+    pub critical_messages: usize,
+    pub paused: bool,
+    pub held_frames: Vec<(FramedResult, Instant, DateTime<Local>)>,
+    pub selected_for_diff: Vec<usize>,
+    pub skipped_bytes: usize,
+    pub repeat_payload: Option<Vec<u8>>,
+    pub last_repeat: Instant,
+    pub file_transfer: Option<FileTransfer>,
+    pub send_dropped: u64,
+    pub dropped_messages: u64,
+    pub label_stats: HashMap<String, LabelStats>,
+    pub next_message_index: u64,
+    /// `(violation_count, consecutive_violations)` per suspect rule, by rule position.
+    pub suspect_runs: Vec<(u64, u64)>,
+}
+
+impl Connection {
+    /// A disconnected tab for `address`.
+    pub fn new(address: String) -> Self {
+        Self {
+            address_input: address,
+            transport: crate::app::net::Transport::Tcp,
+            serial_baud: crate::app::net::DEFAULT_SERIAL_BAUD,
+            is_connected: false,
+            connecting: None,
+            connect_error: None,
+            peer_addr: None,
+            connected_since: None,
+            tx_to_writer: None,
+            rx_from_reader: None,
+            link_events: None,
+            reconnecting: None,
+            reader_join: None,
+            writer_join: None,
+            link_shutdown: None,
+            incoming_buffer: Vec::new(),
+            last_chunk_at: None,
+            received_messages: VecDeque::new(),
+            critical_messages: 0,
+            paused: false,
+            held_frames: Vec::new(),
+            selected_for_diff: Vec::new(),
+            skipped_bytes: 0,
+            repeat_payload: None,
+            last_repeat: Instant::now(),
+            file_transfer: None,
+            send_dropped: 0,
+            dropped_messages: 0,
+            next_message_index: 0,
+            label_stats: HashMap::new(),
+            suspect_runs: Vec::new(),
+        }
+    }
+}
+
 /// Top-level state for the running app.
 pub struct AppState {
+    /// One entry per connection tab; the entry at `active_connection` is an empty
+    /// placeholder while its data is swapped into the fields below.
+    pub connections: Vec<Connection>,
+    pub active_connection: usize,
     /// Address for the TCP connection.
     pub address_input: String,
+    /// Whether `address_input` is dialled over TCP, opened as a UDP socket or names a serial port.
+    pub transport: crate::app::net::Transport,
+    /// Baud rate used when `transport` is `Serial`.
+    pub serial_baud: u32,
+    /// Serial ports found by the last scan, offered in the address bar.
+    pub serial_ports: Vec<String>,
     /// Whether a connection is established.
     pub is_connected: bool,
+    /// Pending result of a background connect, while one is in progress.
+    pub connecting: Option<Receiver<Result<std::net::TcpStream, String>>>,
+    /// Why the last connect attempt failed, shown until the next attempt.
+    pub connect_error: Option<String>,
+    /// Resolved remote address and connect time of the current connection.
+    pub peer_addr: Option<std::net::SocketAddr>,
+    pub connected_since: Option<Instant>,
     /// Channel to the background writer thread.
     pub tx_to_writer: Option<Sender<Vec<u8>>>,
     /// Channel receiving chunks from the background reader thread.
     pub rx_from_reader: Option<Receiver<Vec<u8>>>,
+    /// Reconnect notices from the reader thread.
+    pub link_events: Option<Receiver<crate::app::net::LinkEvent>>,
+    /// Reconnect attempt in progress after the link dropped.
+    pub reconnecting: Option<u32>,
+    /// Throttling shared with the IO threads.
+    pub link_settings: Arc<crate::app::net::LinkSettings>,
 
-    /// Stored recent messages.
-    pub received_messages: Vec<Vec<u8>>,
+    /// Stored recent messages, oldest first, capped at `max_messages`.
+    pub received_messages: VecDeque<ReceivedMessage>,
     pub max_messages: usize,
-    pub display_as_text: bool,
+    /// Count repeats of an identical frame instead of storing it again.
+    pub collapse_duplicates: bool,
+    pub dedup_scope: DedupScope,
+    /// While paused, framed messages wait in `held_frames` instead of the list.
+    pub paused: bool,
+    pub held_frames: Vec<(FramedResult, Instant, DateTime<Local>)>,
+    /// Messages discarded by the `max_messages` cap since the last reset.
+    pub dropped_messages: u64,
+    /// Sequence index of the message whose note is being edited, and the draft text.
+    pub note_editing: Option<u64>,
+    pub note_draft: String,
+    /// Indices into `received_messages` picked for the diff view (at most two).
+    pub selected_for_diff: Vec<usize>,
+    /// Rendering mode for message bodies.
+    pub display_mode: DisplayMode,
+    /// Only show messages with this label; `None` shows all.
+    pub label_filter: Option<String>,
+    /// Only show pinned messages.
+    pub pinned_only: bool,
+    /// Only show messages matching this query; empty shows all.
+    pub message_filter: String,
+    pub message_filter_mode: FilterMode,
+    /// Follow new messages while the list is scrolled to the bottom.
+    pub autoscroll: bool,
+    /// Format used by the "Export messages" action.
+    pub export_format: crate::app::export::ExportFormat,
 
-    /// Start delimiter as space-separated hex (e.g. `AA 55`).
+    /// Start delimiters as space-separated hex; alternatives are comma-separated (e.g. `AA 55, 5A A5`).
     pub start_pattern: String,
     /// End delimiter as space-separated hex (e.g. `0D 0A`).
     pub end_pattern: String,
-    /// Optional data unit size; reserved for future decoding options.
-    pub unit_size: usize,
+    /// Read the start / end pattern as literal text with escapes (`\r`, `\n`, `\xNN`) instead of hex.
+    pub start_pattern_ascii: bool,
+    pub end_pattern_ascii: bool,
+    /// Framing settings, parsed delimiters (cached until the pattern strings or their modes
+    /// change), rules, the partial frame and the sequence index, fed by `process_bytes`.
+    pub engine: EngineState,
+    parsed_start_pattern: Option<(String, bool)>,
+    parsed_end_pattern: Option<(String, bool)>,
+    /// Why the current start and end delimiters could be confused with each other, if they can.
+    pub delimiter_warning: Option<String>,
+    /// Flush a partial frame after this long without new data (ms); 0 is off.
+    pub idle_flush_ms: u64,
+    /// Bytes discarded by framing while searching for start delimiters.
+    pub skipped_bytes: usize,
+    /// Byte order that new watches start with; existing watches keep their own.
+    pub default_endian: Endian,
 
     /// Outgoing bytes to send as space-separated hex.
     pub send_hex_input: String,
+    /// Wrap sent payloads in the framing delimiters (the first start pattern, the end pattern).
+    pub prepend_start_delimiter: bool,
+    pub append_end_delimiter: bool,
+    /// Previously sent inputs, oldest first.
+    pub send_history: Vec<String>,
+    /// Position while recalling history with Up/Down; `None` when not recalling.
+    pub send_history_pos: Option<usize>,
+    /// Named hex commands shown as one-click send buttons, as `(name, hex)`.
+    pub send_presets: Vec<(String, String)>,
+    pub new_preset_name: String,
+    pub new_preset_hex: String,
+    /// CRC calculator: a known-good frame in hex and the range to checksum (blank = all).
+    pub crc_calc_hex: String,
+    pub crc_calc_range: String,
+    /// Bytes re-sent every `repeat_interval_ms` while a repeating send is active.
+    pub repeat_payload: Option<Vec<u8>>,
+    pub repeat_interval_ms: u64,
+    pub last_repeat: Instant,
+    /// Active "Send file" transfer, if any.
+    pub file_transfer: Option<FileTransfer>,
+    /// Sends dropped because the writer queue was full.
+    pub send_dropped: u64,
+    /// Bytes per chunk for file sends; 0 sends the whole file at once.
+    pub file_chunk_size: usize,
+    /// Delay between file chunks in milliseconds.
+    pub file_chunk_delay_ms: u64,
+    /// Send box payloads longer than `file_chunk_size` go out in paced chunks like a file.
+    pub chunk_large_sends: bool,
+    /// Active raw session recording, if any.
+    pub recorder: Option<crate::app::recording::SessionRecorder>,
+    /// Recording being fed back through the framing pipeline, if any.
+    pub replay: Option<crate::app::recording::SessionReplay>,
+
+    /// Messages seen per label (`UNLABELED` for unmatched ones), for the traffic mix strip.
+    pub label_stats: HashMap<String, LabelStats>,
 
-    /// Watch items and form state.
-    pub watch_items: Vec<WatchItem>,
+    // Watch statistics and form state
+    /// Numeric statistics per watch name, accumulated as messages arrive.
+    pub watch_stats: HashMap<String, WatchStats>,
+    /// Most recent numeric values per watch name, newest last, for sparklines.
+    pub watch_history: HashMap<String, VecDeque<f64>>,
+    /// Number of values kept in each `watch_history` entry.
+    pub watch_history_len: usize,
     pub new_watch_name: String,
     pub new_watch_range: String,
     pub edit_watch_idx: Option<usize>,
@@ -116,30 +715,47 @@ pub struct AppState {
     pub edit_watch_range: String,
     pub new_watch_view: WatchView,
     pub edit_watch_view: WatchView,
+    pub new_watch_endian: Endian,
+    pub edit_watch_endian: Endian,
     pub new_watch_target: WatchTarget,
     pub edit_watch_target: WatchTarget,
+    /// Enum value names as typed in the watch forms (`1=PING, 2=REQUEST`).
+    pub new_watch_enum: String,
+    pub edit_watch_enum: String,
+    /// Parse errors shown under the watch range fields.
+    pub new_watch_error: Option<String>,
+    pub edit_watch_error: Option<String>,
 
-    /// Message label rules and form state.
-    pub label_rules: Vec<LabelRule>,
+    // Message label rule form state
     pub new_label_name: String,
-    pub new_label_range: String,
-    pub new_label_value_hex: String,
+    pub new_label_conditions: Vec<LabelConditionForm>,
+    pub new_label_color: [u8; 3],
     pub edit_label_idx: Option<usize>,
     pub edit_label_name: String,
-    pub edit_label_range: String,
-    pub edit_label_value_hex: String,
+    pub edit_label_conditions: Vec<LabelConditionForm>,
+    pub edit_label_color: [u8; 3],
+    /// Parse errors shown under the label condition rows.
+    pub new_label_error: Option<String>,
+    pub edit_label_error: Option<String>,
 
     /// Active left panel tab.
     pub left_panel_tab: LeftPanelTab,
+    /// Byte last clicked in a hex view; shift-click makes a range from here.
+    pub range_click_anchor: Option<usize>,
+    /// Inclusive byte range picked by clicking a hex view, offered for the rule forms.
+    pub selected_range: Option<(usize, usize)>,
 
-    // Suspected data rules and form state
-    pub suspect_rules: Vec<crate::app::suspects::SuspectRule>,
+    // Suspected data rule form state
     pub new_suspect_name: String,
     pub new_suspect_range: String,
     pub new_suspect_kind: crate::app::suspects::ExpectedKind,
     pub new_suspect_value: String,
     pub new_suspect_target: WatchTarget,
     pub new_suspect_severity: crate::app::suspects::Severity,
+    pub new_suspect_trim: bool,
+    pub new_suspect_escalate: u64,
+    /// "Stored at" range of a checksum expectation.
+    pub new_suspect_stored: String,
     pub edit_suspect_idx: Option<usize>,
     pub edit_suspect_name: String,
     pub edit_suspect_range: String,
@@ -147,26 +763,106 @@ pub struct AppState {
     pub edit_suspect_value: String,
     pub edit_suspect_target: WatchTarget,
     pub edit_suspect_severity: crate::app::suspects::Severity,
+    pub edit_suspect_trim: bool,
+    pub edit_suspect_escalate: u64,
+    pub edit_suspect_stored: String,
+    /// Parse errors shown under the suspect range and expected value fields.
+    pub new_suspect_range_error: Option<String>,
+    pub new_suspect_value_error: Option<String>,
+    pub edit_suspect_range_error: Option<String>,
+    pub edit_suspect_value_error: Option<String>,
+
+    /// Stored messages with a Critical warning; the UI turns red while any remain.
+    pub critical_messages: usize,
+    /// Also raise OS desktop notifications for new Critical violations.
+    pub os_notifications: bool,
+    pub notifier: crate::app::notify::CriticalNotifier,
 
-    /// Global critical alert flag for UI feedback.
-    pub critical_active: bool,
+    /// Base UI theme.
+    pub theme: Theme,
+    /// UI zoom factor, clamped to `MIN_ZOOM..=MAX_ZOOM`.
+    pub zoom: f32,
+    /// Render hex digits in upper case (`AA55`) rather than lower case (`aa55`).
+    pub hex_uppercase: bool,
+    /// Show a byte index ruler above hex message bodies.
+    pub show_hex_ruler: bool,
+    /// Render text bodies and Text watches with `\xNN` escapes instead of lossy replacement.
+    pub escape_text: bool,
+    /// Bytes per row in the hexdump display mode.
+    pub hexdump_width: usize,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
+            connections: vec![Connection::new(String::new())],
+            active_connection: 0,
             address_input: "127.0.0.1:9000".to_string(),
+            transport: crate::app::net::Transport::Tcp,
+            serial_baud: crate::app::net::DEFAULT_SERIAL_BAUD,
+            serial_ports: Vec::new(),
             is_connected: false,
+            connecting: None,
+            connect_error: None,
+            peer_addr: None,
+            connected_since: None,
             tx_to_writer: None,
             rx_from_reader: None,
-            received_messages: Vec::new(),
+            link_events: None,
+            reconnecting: None,
+            link_settings: Arc::default(),
+            received_messages: VecDeque::new(),
+            collapse_duplicates: false,
+            dedup_scope: DedupScope::Consecutive,
+            paused: false,
+            held_frames: Vec::new(),
+            dropped_messages: 0,
+            note_editing: None,
+            note_draft: String::new(),
+            selected_for_diff: Vec::new(),
             max_messages: 200,
-            display_as_text: false,
+            display_mode: DisplayMode::Hex,
+            label_filter: None,
+            pinned_only: false,
+            message_filter: String::new(),
+            message_filter_mode: FilterMode::TextContains,
+            autoscroll: true,
+            export_format: crate::app::export::ExportFormat::HexLines,
             start_pattern: "AA 55".to_string(),
             end_pattern: "0D 0A".to_string(),
-            unit_size: 1,
+            idle_flush_ms: 0,
+            start_pattern_ascii: false,
+            end_pattern_ascii: false,
+            engine: EngineState::default(),
+            parsed_start_pattern: None,
+            parsed_end_pattern: None,
+            delimiter_warning: None,
+            skipped_bytes: 0,
+            default_endian: Endian::Little,
             send_hex_input: String::new(),
-            watch_items: Vec::new(),
+            prepend_start_delimiter: false,
+            append_end_delimiter: false,
+            send_history: Vec::new(),
+            send_history_pos: None,
+            send_presets: Vec::new(),
+            new_preset_name: String::new(),
+            new_preset_hex: String::new(),
+            crc_calc_hex: String::new(),
+            crc_calc_range: String::new(),
+            repeat_payload: None,
+            repeat_interval_ms: 500,
+            last_repeat: Instant::now(),
+            file_transfer: None,
+            send_dropped: 0,
+            file_chunk_size: 256,
+            file_chunk_delay_ms: 10,
+            chunk_large_sends: true,
+            recorder: None,
+            replay: None,
+            label_stats: HashMap::new(),
+            watch_stats: HashMap::new(),
+            watch_history: HashMap::new(),
+            watch_history_len: 100,
             new_watch_name: String::new(),
             new_watch_range: String::new(),
             edit_watch_idx: None,
@@ -174,18 +870,26 @@ impl Default for AppState {
             edit_watch_range: String::new(),
             new_watch_view: WatchView::Hex,
             edit_watch_view: WatchView::Hex,
+            new_watch_endian: Endian::Little,
+            edit_watch_endian: Endian::Little,
             new_watch_target: WatchTarget::All,
             edit_watch_target: WatchTarget::All,
-            label_rules: Vec::new(),
+            new_watch_enum: String::new(),
+            edit_watch_enum: String::new(),
+            new_watch_error: None,
+            edit_watch_error: None,
             new_label_name: String::new(),
-            new_label_range: String::new(),
-            new_label_value_hex: String::new(),
+            new_label_conditions: vec![LabelConditionForm::default()],
+            new_label_color: DEFAULT_LABEL_COLOR,
             edit_label_idx: None,
             edit_label_name: String::new(),
-            edit_label_range: String::new(),
-            edit_label_value_hex: String::new(),
+            edit_label_conditions: Vec::new(),
+            edit_label_color: DEFAULT_LABEL_COLOR,
+            new_label_error: None,
+            edit_label_error: None,
             left_panel_tab: LeftPanelTab::Watch,
-            suspect_rules: Vec::new(),
+            range_click_anchor: None,
+            selected_range: None,
             new_suspect_name: String::new(),
             new_suspect_range: String::new(),
             new_suspect_kind: crate::app::suspects::ExpectedKind::Text,
@@ -199,7 +903,376 @@ impl Default for AppState {
             edit_suspect_target: WatchTarget::All,
             new_suspect_severity: crate::app::suspects::Severity::Warning,
             edit_suspect_severity: crate::app::suspects::Severity::Warning,
-            critical_active: false,
+            new_suspect_trim: false,
+            edit_suspect_trim: false,
+            new_suspect_escalate: 0,
+            edit_suspect_escalate: 0,
+            new_suspect_stored: String::new(),
+            edit_suspect_stored: String::new(),
+            new_suspect_range_error: None,
+            new_suspect_value_error: None,
+            edit_suspect_range_error: None,
+            edit_suspect_value_error: None,
+            critical_messages: 0,
+            os_notifications: false,
+            notifier: Default::default(),
+            theme: Theme::Dark,
+            zoom: 1.0,
+            escape_text: false,
+            hex_uppercase: true,
+            show_hex_ruler: true,
+            hexdump_width: HEXDUMP_WIDTH,
+        }
+    }
+}
+
+impl AppState {
+    /// Exchange the selected connection's state with `other`.
+    ///
+    /// The IO thread handles live outside `AppState`, so they are swapped with
+    /// `other.last_chunk_at`, `other.reader_join`, `other.writer_join` and
+    /// `other.link_shutdown` by the caller.
+    pub fn swap_connection(&mut self, other: &mut Connection) {
+        std::mem::swap(&mut self.address_input, &mut other.address_input);
+        std::mem::swap(&mut self.transport, &mut other.transport);
+        std::mem::swap(&mut self.serial_baud, &mut other.serial_baud);
+        std::mem::swap(&mut self.is_connected, &mut other.is_connected);
+        std::mem::swap(&mut self.connecting, &mut other.connecting);
+        std::mem::swap(&mut self.connect_error, &mut other.connect_error);
+        std::mem::swap(&mut self.peer_addr, &mut other.peer_addr);
+        std::mem::swap(&mut self.connected_since, &mut other.connected_since);
+        std::mem::swap(&mut self.tx_to_writer, &mut other.tx_to_writer);
+        std::mem::swap(&mut self.rx_from_reader, &mut other.rx_from_reader);
+        std::mem::swap(&mut self.link_events, &mut other.link_events);
+        std::mem::swap(&mut self.reconnecting, &mut other.reconnecting);
+        std::mem::swap(&mut self.received_messages, &mut other.received_messages);
+        std::mem::swap(&mut self.critical_messages, &mut other.critical_messages);
+        std::mem::swap(&mut self.paused, &mut other.paused);
+        std::mem::swap(&mut self.held_frames, &mut other.held_frames);
+        std::mem::swap(&mut self.selected_for_diff, &mut other.selected_for_diff);
+        std::mem::swap(&mut self.skipped_bytes, &mut other.skipped_bytes);
+        std::mem::swap(&mut self.repeat_payload, &mut other.repeat_payload);
+        std::mem::swap(&mut self.last_repeat, &mut other.last_repeat);
+        std::mem::swap(&mut self.file_transfer, &mut other.file_transfer);
+        std::mem::swap(&mut self.send_dropped, &mut other.send_dropped);
+        std::mem::swap(&mut self.dropped_messages, &mut other.dropped_messages);
+        std::mem::swap(&mut self.engine.buffer, &mut other.incoming_buffer);
+        std::mem::swap(&mut self.engine.next_index, &mut other.next_message_index);
+        std::mem::swap(&mut self.label_stats, &mut other.label_stats);
+        // Rules are shared but their violation counters belong to the stream they ran over
+        let runs = self.engine.suspect_rules.iter().map(|r| (r.violation_count, r.consecutive_violations)).collect();
+        for (i, rule) in self.engine.suspect_rules.iter_mut().enumerate() {
+            (rule.violation_count, rule.consecutive_violations) = other.suspect_runs.get(i).copied().unwrap_or_default();
+        }
+        other.suspect_runs = runs;
+    }
+
+    /// Parse `input` as hex and queue it for the writer thread; returns the byte count.
+    ///
+    /// With `chunk_large_sends`, payloads over `file_chunk_size` start a chunked transfer instead.
+    pub fn send_hex(&mut self, input: &str) -> Result<usize, String> {
+        let bytes = self.frame_outgoing(parse_hex_bytes(input)?);
+        if self.chunk_large_sends && self.file_chunk_size > 0 && bytes.len() > self.file_chunk_size {
+            if self.tx_to_writer.is_none() {
+                return Err("not connected".to_string());
+            }
+            if self.file_transfer.is_some() {
+                return Err("a chunked send is already in progress".to_string());
+            }
+            let len = bytes.len();
+            self.start_chunked_send("Send box".to_string(), bytes);
+            return Ok(len);
+        }
+        self.queue_send(bytes)
+    }
+
+    /// Start sending `data` in `file_chunk_size` pieces, `file_chunk_delay_ms` apart.
+    pub fn start_chunked_send(&mut self, name: String, data: Vec<u8>) {
+        let now = Instant::now();
+        self.file_transfer = Some(FileTransfer {
+            name,
+            data,
+            sent: 0,
+            // Backdate so the first chunk goes out immediately
+            last_chunk: now.checked_sub(std::time::Duration::from_millis(self.file_chunk_delay_ms)).unwrap_or(now),
+        });
+    }
+
+    /// Queue `bytes` for the writer thread without blocking the UI.
+    ///
+    /// When the writer can't keep up and its queue is full the bytes are dropped and
+    /// counted in `send_dropped`.
+    pub fn queue_send(&mut self, bytes: Vec<u8>) -> Result<usize, String> {
+        let tx = self.tx_to_writer.as_ref().ok_or_else(|| "not connected".to_string())?;
+        let len = bytes.len();
+        match tx.try_send(bytes) {
+            Ok(()) => Ok(len),
+            Err(TrySendError::Full(_)) => {
+                self.send_dropped += 1;
+                Err("send buffer full; the peer isn't reading".to_string())
+            }
+            Err(TrySendError::Disconnected(_)) => Err("send failed: writer stopped".to_string()),
+        }
+    }
+
+    /// Add the delimiters selected by `prepend_start_delimiter` / `append_end_delimiter` around `payload`.
+    pub fn frame_outgoing(&self, payload: Vec<u8>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(payload.len() + 8);
+        if self.prepend_start_delimiter {
+            if let Some(start) = self.engine.start_bytes.first() {
+                bytes.extend_from_slice(start);
+            }
+        }
+        bytes.extend(payload);
+        if self.append_end_delimiter {
+            bytes.extend_from_slice(&self.engine.end_bytes);
+        }
+        bytes
+    }
+
+    /// Remember a successfully sent input, skipping consecutive duplicates.
+    pub fn record_send(&mut self, input: &str) {
+        let entry = input.trim();
+        if !entry.is_empty() && self.send_history.last().map(String::as_str) != Some(entry) {
+            self.send_history.push(entry.to_string());
+            if self.send_history.len() > MAX_SEND_HISTORY {
+                self.send_history.remove(0);
+            }
+        }
+        self.send_history_pos = None;
+    }
+
+    /// Step through the send history like a shell; `older` moves back in time.
+    pub fn recall_send_history(&mut self, older: bool) {
+        if self.send_history.is_empty() { return; }
+        let last = self.send_history.len() - 1;
+        self.send_history_pos = match (self.send_history_pos, older) {
+            (None, true) => Some(last),
+            (Some(p), true) => Some(p.saturating_sub(1)),
+            (Some(p), false) if p < last => Some(p + 1),
+            (_, false) => None,
+        };
+        self.send_hex_input = match self.send_history_pos {
+            Some(p) => self.send_history[p].clone(),
+            None => String::new(),
+        };
+    }
+
+    /// Put `range` into the range field of the form open on the current left panel tab:
+    /// the item being edited if any, otherwise the add form (its last condition for labels).
+    pub fn set_active_form_range(&mut self, range: String) {
+        let field = match self.left_panel_tab {
+            LeftPanelTab::Watch if self.edit_watch_idx.is_some() => Some(&mut self.edit_watch_range),
+            LeftPanelTab::Watch => Some(&mut self.new_watch_range),
+            LeftPanelTab::Labels if self.edit_label_idx.is_some() => self.edit_label_conditions.last_mut().map(|c| &mut c.range),
+            LeftPanelTab::Labels => self.new_label_conditions.last_mut().map(|c| &mut c.range),
+            LeftPanelTab::Suspects if self.edit_suspect_idx.is_some() => Some(&mut self.edit_suspect_range),
+            LeftPanelTab::Suspects => Some(&mut self.new_suspect_range),
+        };
+        if let Some(field) = field {
+            *field = range;
+        }
+    }
+
+    /// Switch the left panel to `tab` and put `selected_range` into its open form.
+    pub fn use_selected_range(&mut self, tab: LeftPanelTab) {
+        let Some((start, end)) = self.selected_range else { return };
+        self.left_panel_tab = tab;
+        self.set_active_form_range(format_index_range(start, end));
+    }
+
+    /// Re-parse the delimiter patterns only if their source strings or modes changed.
+    pub fn refresh_delimiters(&mut self) {
+        let mut changed = false;
+        let start_key = (self.start_pattern.clone(), self.start_pattern_ascii);
+        if self.parsed_start_pattern.as_ref() != Some(&start_key) {
+            self.engine.start_bytes = parse_start_patterns(&self.start_pattern, self.start_pattern_ascii);
+            self.parsed_start_pattern = Some(start_key);
+            changed = true;
+        }
+        let end_key = (self.end_pattern.clone(), self.end_pattern_ascii);
+        if self.parsed_end_pattern.as_ref() != Some(&end_key) {
+            self.engine.end_bytes = parse_pattern(&self.end_pattern, self.end_pattern_ascii).unwrap_or_default();
+            self.parsed_end_pattern = Some(end_key);
+            changed = true;
+        }
+        if changed {
+            self.delimiter_warning = delimiter_overlap_warning(&self.engine.start_bytes, &self.engine.end_bytes);
+        }
+    }
+
+    /// Store a message evaluated by the engine, dropping the oldest ones once `max_messages`
+    /// is exceeded, and update per-label counts and watch statistics.
+    ///
+    /// With `collapse_duplicates` on, a frame equal to a message in `dedup_scope` bumps that
+    /// entry's repeat count instead.
+    pub fn push_message(&mut self, result: FramedResult) {
+        self.push_message_at(result, Instant::now(), Local::now());
+    }
+
+    /// `push_message` for a frame that completed at `received_at` / `wall_clock`.
+    pub fn push_message_at(&mut self, result: FramedResult, received_at: Instant, wall_clock: DateTime<Local>) {
+        let fingerprint = ChecksumAlgo::Crc32.compute(&result.bytes);
+        self.label_stats
+            .entry(result.label.clone().unwrap_or_else(|| UNLABELED.to_string()))
+            .or_default()
+            .record(received_at);
+        self.accumulate_message(&result, true);
+        if self.collapse_duplicates {
+            let window = match self.dedup_scope {
+                DedupScope::Consecutive => 1,
+                DedupScope::Recent => DEDUP_WINDOW,
+            };
+            if let Some(existing) = self.received_messages.iter_mut().rev().take(window).find(|m| m.fingerprint == fingerprint && m.bytes == result.bytes) {
+                existing.repeat += 1;
+                return;
+            }
+        }
+        let FramedResult { bytes, start_pattern, status, index, label, warnings, .. } = result;
+        let entry = ReceivedMessage { bytes, start_pattern, repeat: 1, pinned: false, status, index, fingerprint, label, warnings, note: String::new(), card_height: 0.0, received_at, wall_clock };
+        if entry.is_critical() {
+            self.critical_messages += 1;
+        }
+        self.received_messages.push_back(entry);
+        self.trim_messages();
+    }
+
+    /// Add one evaluated message to the watch statistics; live messages also raise
+    /// notifications for their critical warnings.
+    fn accumulate_message(&mut self, result: &FramedResult, live: bool) {
+        if live && self.os_notifications {
+            for (_, text) in result.warnings.iter().filter(|(sev, _)| *sev == crate::app::suspects::Severity::Critical) {
+                self.notifier.notify(text);
+            }
+        }
+        for reading in &result.watches {
+            let Some(value) = reading.value else { continue };
+            self.watch_stats.entry(reading.name.clone()).or_default().record(value);
+            let history = self.watch_history.entry(reading.name.clone()).or_default();
+            history.push_back(value);
+            while history.len() > self.watch_history_len.max(1) {
+                history.pop_front();
+            }
+        }
+    }
+
+    /// Rebuild suspect violation counts, watch statistics and each stored message's warnings
+    /// by replaying the current rules over the stored messages, counting collapsed repeats.
+    /// Messages already dropped by the cap are gone and not counted; per-label traffic counts
+    /// are left as they are.
+    pub fn reevaluate_all(&mut self) {
+        self.watch_stats.clear();
+        self.watch_history.clear();
+        for rule in &mut self.engine.suspect_rules {
+            rule.violation_count = 0;
+            rule.consecutive_violations = 0;
+        }
+        let mut messages = std::mem::take(&mut self.received_messages);
+        for entry in &mut messages {
+            for repeat in 0..entry.repeat {
+                let result = self.engine.evaluate_at(entry.framed(), entry.index);
+                self.accumulate_message(&result, false);
+                if repeat == 0 {
+                    entry.label = result.label;
+                    entry.warnings = result.warnings;
+                }
+            }
+        }
+        self.received_messages = messages;
+        self.critical_messages = self.received_messages.iter().filter(|m| m.is_critical()).count();
+    }
+
+    /// Re-label the stored messages and recheck their warnings after the suspect or label rules changed.
+    ///
+    /// Violation counts are left alone; runs of violations, and so escalation, are replayed
+    /// from the oldest stored message.
+    pub fn refresh_annotations(&mut self) {
+        let mut scratch = EngineState {
+            label_rules: self.engine.label_rules.clone(),
+            suspect_rules: self.engine.suspect_rules.clone(),
+            ..Default::default()
+        };
+        for rule in &mut scratch.suspect_rules {
+            rule.consecutive_violations = 0;
+        }
+        for entry in &mut self.received_messages {
+            let result = scratch.evaluate_at(entry.framed(), entry.index);
+            entry.label = result.label;
+            entry.warnings = result.warnings;
+        }
+        self.critical_messages = self.received_messages.iter().filter(|m| m.is_critical()).count();
+    }
+
+    /// Zero every counter and statistic while keeping messages and rules: per-label counts,
+    /// suspect violation counts, watch statistics and history, skipped bytes and dropped sends and messages.
+    pub fn reset_stats(&mut self) {
+        self.label_stats.clear();
+        self.watch_stats.clear();
+        self.watch_history.clear();
+        for rule in &mut self.engine.suspect_rules {
+            rule.violation_count = 0;
+            rule.consecutive_violations = 0;
+        }
+        self.skipped_bytes = 0;
+        self.send_dropped = 0;
+        self.dropped_messages = 0;
+    }
+
+    /// Pause or resume capture; resuming stores the held frames in arrival order.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if !paused {
+            for (framed, received_at, wall_clock) in std::mem::take(&mut self.held_frames) {
+                self.push_message_at(framed, received_at, wall_clock);
+            }
+        }
+    }
+
+    /// Drop the oldest unpinned messages until at most `max_messages` remain (pinned ones may exceed it).
+    ///
+    /// Runs in one pass, so lowering the cap on a long list stays cheap.
+    pub fn trim_messages(&mut self) {
+        let excess = self.received_messages.len().saturating_sub(self.max_messages);
+        if excess == 0 { return; }
+        // Indices of the messages to drop, ascending
+        let dropped: Vec<usize> = self
+            .received_messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| !m.pinned)
+            .map(|(i, _)| i)
+            .take(excess)
+            .collect();
+        self.critical_messages -= dropped.iter().filter(|&&i| self.received_messages[i].is_critical()).count();
+        if dropped.last().is_some_and(|&last| last + 1 == dropped.len()) {
+            // No pinned message among the oldest: drop them as one block
+            self.received_messages.drain(..dropped.len());
+        } else {
+            let mut i = 0;
+            self.received_messages.retain(|_| {
+                let keep = dropped.binary_search(&i).is_err();
+                i += 1;
+                keep
+            });
+        }
+        self.dropped_messages += dropped.len() as u64;
+        // Keep diff selections pointing at the same messages
+        self.selected_for_diff = self
+            .selected_for_diff
+            .iter()
+            .filter_map(|&i| dropped.binary_search(&i).err().map(|before| i - before))
+            .collect();
+    }
+
+    /// Add or remove a message from the diff selection, keeping at most the two latest picks.
+    pub fn toggle_diff_selection(&mut self, index: usize) {
+        if let Some(pos) = self.selected_for_diff.iter().position(|&i| i == index) {
+            self.selected_for_diff.remove(pos);
+        } else {
+            self.selected_for_diff.push(index);
+            if self.selected_for_diff.len() > 2 {
+                self.selected_for_diff.remove(0);
+            }
         }
     }
 }
@@ -218,53 +1291,524 @@ pub fn parse_hex_bytes(input: &str) -> Result<Vec<u8>, String> {
     Ok(bytes)
 }
 
+/// Parse text with backslash escapes into bytes: `\r`, `\n`, `\t`, `\0`, `\\` and `\xNN`.
+pub fn parse_escaped_text(input: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                let b = u8::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape '\\x{}'", digits))?;
+                bytes.push(b);
+            }
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Length of an incomplete multi-byte UTF-8 sequence at the end of `bytes`, or 0.
+fn pending_utf8_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        // Walk back over continuation bytes to the lead byte
+        if bytes[bytes.len() - back] & 0xC0 == 0x80 { continue; }
+        return match std::str::from_utf8(&bytes[bytes.len() - back..]) {
+            Err(e) if e.valid_up_to() == 0 && e.error_len().is_none() => back,
+            _ => 0,
+        };
+    }
+    0
+}
+
+/// Note shown in place of a trailing incomplete UTF-8 sequence.
+fn pending_utf8_note(pending: usize) -> String {
+    format!("…({} byte{} pending)", pending, if pending == 1 { "" } else { "s" })
+}
+
+/// Render bytes as text with lossy replacement, except that a multi-byte character cut off
+/// at the end is shown as a pending note instead of `�`.
+pub fn format_text(bytes: &[u8]) -> String {
+    let pending = pending_utf8_len(bytes);
+    let mut out = String::from_utf8_lossy(&bytes[..bytes.len() - pending]).to_string();
+    if pending > 0 {
+        out.push_str(&pending_utf8_note(pending));
+    }
+    out
+}
+
+/// Render bytes as text on one line, drawing C0 control bytes and DEL as their Unicode
+/// control pictures (`\r` as `␍`, `\n` as `␊`). Invalid UTF-8 is replaced with `�`.
+pub fn format_control_pictures(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .chars()
+        .map(|c| match c {
+            '\0'..='\x1F' => char::from_u32(0x2400 + c as u32).unwrap_or(c),
+            '\x7F' => '\u{2421}',
+            c => c,
+        })
+        .collect()
+}
+
+/// Render bytes as text, escaping anything that is not printable UTF-8.
+///
+/// The inverse of `parse_escaped_text`: invalid UTF-8 and control bytes become `\xNN`
+/// (or `\r`, `\n`, `\t`, `\0`), and a literal backslash becomes `\\`.
+pub fn format_escaped_text(bytes: &[u8]) -> String {
+    let pending = pending_utf8_len(bytes);
+    let mut out = String::new();
+    for chunk in bytes[..bytes.len() - pending].utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\r' => out.push_str("\\r"),
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\0' => out.push_str("\\0"),
+                '\\' => out.push_str("\\\\"),
+                c if c.is_control() => {
+                    let mut buf = [0u8; 4];
+                    for b in c.encode_utf8(&mut buf).bytes() {
+                        out.push_str(&format!("\\x{:02X}", b));
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        for b in chunk.invalid() {
+            out.push_str(&format!("\\x{:02X}", b));
+        }
+    }
+    if pending > 0 {
+        out.push_str(&pending_utf8_note(pending));
+    }
+    out
+}
+
+/// Parse one delimiter pattern as hex, or as escaped text when `ascii` is set.
+pub fn parse_pattern(input: &str, ascii: bool) -> Result<Vec<u8>, String> {
+    if ascii { parse_escaped_text(input) } else { parse_hex_bytes(input) }
+}
+
+/// Parse comma-separated alternative start delimiters, skipping ones that don't parse.
+///
+/// In ASCII mode a literal comma is written `\x2C`.
+pub fn parse_start_patterns(input: &str, ascii: bool) -> Vec<Vec<u8>> {
+    input
+        .split(',')
+        .filter_map(|p| parse_pattern(p, ascii).ok())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
 /// Parse an index or range string (e.g. `3`, `3-5`, `10..20`).
+///
+/// Also accepts open-ended `10..` (to the end of the message, as `WHOLE_MESSAGE_END`),
+/// `..8` (from the first byte) and count-based `10:+4` (four bytes starting at 10).
 pub fn parse_index_range(input: &str) -> Option<(usize, usize)> {
     let s = input.trim();
     if s.is_empty() { return None; }
-    if let Some((a, b)) = s.split_once('-') {
+    if let Some((a, b)) = s.split_once(":+") {
         let start = a.trim().parse::<usize>().ok()?;
-        let end = b.trim().parse::<usize>().ok()?;
+        let count = b.trim().parse::<usize>().ok()?;
+        if count == 0 { return None; }
+        Some((start, start.checked_add(count - 1)?))
+    } else if let Some((a, b)) = s.split_once("..") {
+        let (a, b) = (a.trim(), b.trim());
+        if a.is_empty() && b.is_empty() { return None; }
+        let start = if a.is_empty() { 0 } else { a.parse::<usize>().ok()? };
+        let end = if b.is_empty() { WHOLE_MESSAGE_END } else { b.parse::<usize>().ok()? };
         Some((start, end))
-    } else if let Ok(idx) = s.parse::<usize>() {
-        Some((idx, idx))
-    } else if let Some((a, b)) = s.split_once("..") { // also support "start..end"
+    } else if let Some((a, b)) = s.split_once('-') {
         let start = a.trim().parse::<usize>().ok()?;
         let end = b.trim().parse::<usize>().ok()?;
         Some((start, end))
+    } else if let Ok(idx) = s.parse::<usize>() {
+        Some((idx, idx))
     } else {
         None
     }
 }
 
-/// Render bytes according to a `WatchView`.
-pub fn format_bytes_for_view(bytes: &[u8], view: WatchView) -> String {
+/// Parse a form's range field with its bounds in order, or describe why it failed.
+pub fn parse_range_field(input: &str) -> Result<(usize, usize), String> {
+    match parse_index_range(input) {
+        Some((s, e)) => Ok(if s <= e { (s, e) } else { (e, s) }),
+        None if input.trim().is_empty() => Err("range is required".to_string()),
+        None => Err(format!("could not parse range '{}'", input.trim())),
+    }
+}
+
+/// Resolve an inclusive range against a message of `len` bytes.
+///
+/// The end (including `WHOLE_MESSAGE_END`) is clamped to the last byte; `None` if the
+/// range starts past the end of the message.
+pub fn resolve_range(start: usize, end: usize, len: usize) -> Option<(usize, usize)> {
+    if start > end || start >= len { return None; }
+    Some((start, end.min(len - 1)))
+}
+
+/// Decode up to 8 bytes as an unsigned integer in the given byte order.
+pub fn decode_unsigned(bytes: &[u8], endian: Endian) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 8 { return None; }
+    let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+    Some(match endian {
+        Endian::Big => bytes.iter().fold(0, fold),
+        Endian::Little => bytes.iter().rev().fold(0, fold),
+    })
+}
+
+/// Decode up to 8 bytes as a two's-complement signed integer in the given byte order.
+pub fn decode_signed(bytes: &[u8], endian: Endian) -> Option<i64> {
+    let value = decode_unsigned(bytes, endian)?;
+    let shift = 64 - 8 * bytes.len() as u32;
+    Some(((value << shift) as i64) >> shift)
+}
+
+/// Format an inclusive range the way `parse_index_range` reads it back.
+pub fn format_index_range(start: usize, end: usize) -> String {
+    if end == WHOLE_MESSAGE_END {
+        format!("{}..", start)
+    } else if start == end {
+        start.to_string()
+    } else {
+        format!("{}-{}", start, end)
+    }
+}
+
+/// Split `bytes` into groups of `unit_size`, reversing each full group for little-endian.
+fn byte_groups(bytes: &[u8], unit_size: usize, endian: Endian) -> Vec<Vec<u8>> {
+    bytes
+        .chunks(unit_size.max(1))
+        .map(|chunk| {
+            let mut group = chunk.to_vec();
+            if endian == Endian::Little && chunk.len() == unit_size {
+                group.reverse();
+            }
+            group
+        })
+        .collect()
+}
+
+/// Encode bytes as contiguous hex digits in upper or lower case.
+pub fn encode_hex(bytes: &[u8], uppercase: bool) -> String {
+    if uppercase { hex::encode_upper(bytes) } else { hex::encode(bytes) }
+}
+
+/// Render bytes according to a `WatchView`, grouping hex and binary output
+/// into words of `unit_size` bytes ordered by `endian`.
+pub fn format_bytes_for_view(bytes: &[u8], view: WatchView, unit_size: usize, endian: Endian, uppercase: bool) -> String {
     match view {
-        WatchView::Hex => hex::encode_upper(bytes),
-        WatchView::Text => String::from_utf8_lossy(bytes).to_string(),
-        WatchView::Binary => {
-            let mut out = String::new();
-            for (i, b) in bytes.iter().enumerate() {
-                if i > 0 { out.push(' '); }
-                out.push_str(&format!("{:08b}", b));
+        WatchView::Hex => byte_groups(bytes, unit_size, endian)
+            .iter()
+            .map(|g| encode_hex(g, uppercase))
+            .collect::<Vec<_>>()
+            .join(" "),
+        WatchView::Text => format_text(bytes),
+        WatchView::Binary => byte_groups(bytes, unit_size, endian)
+            .iter()
+            .map(|g| g.iter().map(|b| format!("{:08b}", b)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" "),
+        WatchView::Checksum(algo) if uppercase => algo.compute_hex(bytes),
+        WatchView::Checksum(algo) => algo.compute_hex(bytes).to_lowercase(),
+        // Without a name table an enum is just its number.
+        WatchView::Enum => match decode_unsigned(bytes, endian) {
+            Some(value) => value.to_string(),
+            None => format_bytes_for_view(bytes, WatchView::Hex, unit_size, endian, uppercase),
+        },
+        WatchView::SignedByte => bytes.iter().map(|b| (*b as i8).to_string()).collect::<Vec<_>>().join(", "),
+        WatchView::Integer { width, .. } if bytes.len() != width => "-".to_string(),
+        WatchView::Integer { signed: true, .. } => decode_signed(bytes, endian).map_or_else(|| "-".to_string(), |v| v.to_string()),
+        WatchView::Integer { signed: false, .. } => decode_unsigned(bytes, endian).map_or_else(|| "-".to_string(), |v| v.to_string()),
+    }
+}
+
+/// Bytes per line when rendering a message body as hex.
+pub const HEX_ROW_BYTES: usize = 32;
+
+/// Render bytes as grouped hex, wrapped onto lines of `HEX_ROW_BYTES` bytes.
+pub fn format_hex_rows(bytes: &[u8], unit_size: usize, endian: Endian, uppercase: bool) -> String {
+    bytes
+        .chunks(HEX_ROW_BYTES)
+        .map(|row| format_bytes_for_view(row, WatchView::Hex, unit_size, endian, uppercase))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Bytes between offset labels in the hex index ruler.
+pub const HEX_RULER_STEP: usize = 4;
+
+/// Index ruler for a `format_hex_rows` line of `row_len` bytes, with each labelled offset
+/// (every `HEX_RULER_STEP` bytes, or every group for wider units) above that byte's digits.
+pub fn format_hex_ruler(row_len: usize, unit_size: usize) -> String {
+    let unit = unit_size.max(1);
+    let step = if unit > HEX_RULER_STEP { unit } else { HEX_RULER_STEP };
+    // Each byte is two digits, with a space after every group
+    let column = |k: usize| 2 * k + k / unit;
+    let mut line = vec![b' '; column(row_len) + 2];
+    for k in (0..row_len).step_by(step) {
+        let label = k.to_string();
+        line[column(k)..column(k) + label.len()].copy_from_slice(label.as_bytes());
+    }
+    String::from_utf8_lossy(&line).trim_end().to_string()
+}
+
+/// Default bytes per row in the hexdump rendering.
+pub const HEXDUMP_WIDTH: usize = 16;
+
+/// Render bytes like a hex editor: offset column, hex bytes, and a printable-ASCII gutter.
+pub fn format_hex_dump(bytes: &[u8], width: usize, uppercase: bool) -> String {
+    let width = width.max(1);
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(width).enumerate() {
+        if row > 0 { out.push('\n'); }
+        out.push_str(&encode_hex(&((row * width) as u32).to_be_bytes(), uppercase));
+        out.push_str("  ");
+        for i in 0..width {
+            match chunk.get(i) {
+                Some(b) => {
+                    out.push_str(&encode_hex(&[*b], uppercase));
+                    out.push(' ');
+                }
+                None => out.push_str("   "),
             }
-            out
         }
+        out.push_str(" |");
+        for b in chunk {
+            out.push(if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' });
+        }
+        out.push('|');
     }
+    out
 }
 
-/// Find the first matching label for `message` using `rules`.
-pub fn find_message_label(message: &[u8], rules: &[LabelRule]) -> Option<String> {
-    for r in rules {
-        let start = r.start_index;
-        let end = r.end_index;
-        if start <= end && end < message.len() {
-            let slice = &message[start..=end];
-            if slice.len() == r.value.len() && slice == r.value.as_slice() {
-                return Some(r.name.clone());
+/// Render bytes as a comma-separated `0x`-prefixed array (e.g. `[0xAA, 0x55]`).
+pub fn format_bytes_as_array(bytes: &[u8], uppercase: bool) -> String {
+    let items: Vec<String> = bytes.iter().map(|b| format!("0x{}", encode_hex(&[*b], uppercase))).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Format bytes as space-separated hex suitable for re-parsing with `parse_hex_bytes`.
+pub fn format_hex_input(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// Build a label condition from one form row, compiling the regex in `Regex` mode.
+///
+/// In `Regex` mode a blank range means the whole message. A blank mask compares all bits.
+pub fn build_label_condition(form: &LabelConditionForm) -> Result<LabelCondition, String> {
+    let (start_index, end_index) = match form.mode {
+        LabelMatchMode::Regex if form.range.trim().is_empty() => (0, WHOLE_MESSAGE_END),
+        _ => parse_range_field(&form.range)?,
+    };
+    let (value, mask, regex) = match form.mode {
+        LabelMatchMode::Exact => {
+            let value = parse_hex_bytes(&form.value_hex)?;
+            let mask = parse_hex_bytes(&form.mask_hex)?;
+            if !mask.is_empty() && mask.len() != value.len() {
+                return Err(format!("mask has {} bytes but value has {}", mask.len(), value.len()));
             }
+            (value, mask, None)
+        }
+        LabelMatchMode::Regex => {
+            let re = Regex::new(&form.pattern).map_err(|e| format!("invalid regex: {}", e))?;
+            (Vec::new(), Vec::new(), Some(re))
         }
+    };
+    Ok(LabelCondition {
+        start_index,
+        end_index,
+        value,
+        mask,
+        mode: form.mode,
+        pattern: form.pattern.clone(),
+        regex,
+    })
+}
+
+/// Build a label rule from its name, condition rows, and tint color.
+pub fn build_label_rule(name: &str, conditions: &[LabelConditionForm], color: [u8; 3]) -> Result<LabelRule, String> {
+    if conditions.is_empty() {
+        return Err("a label rule needs at least one condition".to_string());
     }
-    None
+    let conditions = conditions.iter().map(build_label_condition).collect::<Result<Vec<_>, _>>()?;
+    Ok(LabelRule { name: name.to_string(), conditions, color })
 }
 
+/// Format a label condition's range for display and for the edit form.
+pub fn format_label_range(c: &LabelCondition) -> String {
+    if c.start_index == 0 && c.end_index == WHOLE_MESSAGE_END {
+        String::new()
+    } else {
+        format_index_range(c.start_index, c.end_index)
+    }
+}
+
+/// Describe a label condition, e.g. `[3-3] == 01` or `[whole message] =~ /PING/`.
+pub fn format_label_condition(c: &LabelCondition, uppercase: bool) -> String {
+    let range = format_label_range(c);
+    let range = if range.is_empty() { "whole message".to_string() } else { range };
+    match c.mode {
+        LabelMatchMode::Exact if c.mask.is_empty() => format!("[{}] == {}", range, encode_hex(&c.value, uppercase)),
+        LabelMatchMode::Exact => format!("[{}] & {} == {}", range, encode_hex(&c.mask, uppercase), encode_hex(&c.value, uppercase)),
+        LabelMatchMode::Regex => format!("[{}] =~ /{}/", range, c.pattern),
+    }
+}
+
+/// Whether a single label condition matches `message`.
+fn label_condition_matches(message: &[u8], c: &LabelCondition) -> bool {
+    let (start, end) = match resolve_range(c.start_index, c.end_index, message.len()) {
+        Some(r) => r,
+        None => return false,
+    };
+    let slice = &message[start..=end];
+    match c.mode {
+        LabelMatchMode::Exact => {
+            slice.len() == c.value.len()
+                && slice.iter().zip(&c.value).enumerate().all(|(i, (b, v))| {
+                    let m = c.mask.get(i).copied().unwrap_or(0xFF);
+                    b & m == v & m
+                })
+        }
+        LabelMatchMode::Regex => match &c.regex {
+            Some(re) => re.is_match(&String::from_utf8_lossy(slice)),
+            None => false,
+        },
+    }
+}
+
+/// Whether every condition of a label rule matches `message`.
+fn label_rule_matches(message: &[u8], r: &LabelRule) -> bool {
+    !r.conditions.is_empty() && r.conditions.iter().all(|c| label_condition_matches(message, c))
+}
+
+/// Find the first label rule in `rules` that matches `message`.
+pub fn find_message_label_rule<'a>(message: &[u8], rules: &'a [LabelRule]) -> Option<&'a LabelRule> {
+    rules.iter().find(|r| label_rule_matches(message, r))
+}
+
+/// Find the first matching label for `message` using `rules`.
+pub fn find_message_label(message: &[u8], rules: &[LabelRule]) -> Option<String> {
+    find_message_label_rule(message, rules).map(|r| r.name.clone())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Push message `n` with its number as the body.
+    fn push(state: &mut AppState, n: u32) {
+        let framed = FramedMessage { bytes: n.to_le_bytes().to_vec(), skipped: 0, start_pattern: None, status: FrameStatus::Clean };
+        let result = state.engine.evaluate(framed);
+        state.push_message(result);
+    }
+
+    fn stored(state: &AppState) -> Vec<u64> {
+        state.received_messages.iter().map(|m| m.index).collect()
+    }
+
+    fn watch(view: WatchView) -> WatchItem {
+        WatchItem { name: "w".to_string(), start_index: 0, end_index: 1, view, endian: Endian::Big, target: WatchTarget::All, enum_names: Vec::new() }
+    }
+
+    #[test]
+    fn only_numeric_views_have_values() {
+        assert_eq!(watch(WatchView::Integer { width: 2, signed: false }).numeric_value(&[0xFF, 0xFE]), Some(65534.0));
+        assert_eq!(watch(WatchView::Integer { width: 2, signed: true }).numeric_value(&[0xFF, 0xFE]), Some(-2.0));
+        assert_eq!(watch(WatchView::Integer { width: 4, signed: false }).numeric_value(&[0xFF, 0xFE]), None);
+        assert_eq!(watch(WatchView::Enum).numeric_value(&[0x01, 0x02]), Some(258.0));
+        assert_eq!(watch(WatchView::SignedByte).numeric_value(&[0xFF]), Some(-1.0));
+        assert_eq!(watch(WatchView::SignedByte).numeric_value(&[0xFF, 0x01]), None);
+        for view in [WatchView::Hex, WatchView::Text, WatchView::Binary, WatchView::Checksum(crate::app::checksum::ChecksumAlgo::Sum8)] {
+            assert_eq!(watch(view).numeric_value(b"AB"), None);
+        }
+    }
+
+    #[test]
+    fn sustained_overflow_keeps_the_newest() {
+        let mut state = AppState { max_messages: 10, ..Default::default() };
+        for n in 0..10 {
+            push(&mut state, n);
+        }
+        state.toggle_diff_selection(5);
+        state.toggle_diff_selection(8);
+        for n in 10..13 {
+            push(&mut state, n);
+        }
+        assert_eq!(stored(&state), (3..13).collect::<Vec<_>>());
+        // Still the messages numbered 5 and 8
+        assert_eq!(state.selected_for_diff, vec![2, 5]);
+        for n in 13..10_000 {
+            push(&mut state, n);
+            assert_eq!(state.received_messages.len(), 10);
+        }
+        assert_eq!(stored(&state), (9_990..10_000).collect::<Vec<_>>());
+        assert_eq!(state.received_messages.back().unwrap().bytes, 9_999u32.to_le_bytes());
+        assert!(state.selected_for_diff.is_empty());
+        assert_eq!(state.dropped_messages, 9_990);
+    }
+
+    #[test]
+    fn overflow_skips_pinned_messages() {
+        let mut state = AppState { max_messages: 4, ..Default::default() };
+        for n in 0..4 {
+            push(&mut state, n);
+        }
+        state.received_messages[1].pinned = true;
+        state.toggle_diff_selection(2);
+        for n in 4..8 {
+            push(&mut state, n);
+        }
+        assert_eq!(stored(&state), vec![1, 5, 6, 7]);
+        assert!(state.selected_for_diff.is_empty());
+        // Message 6 moves up when 5, the oldest unpinned one, is dropped
+        state.toggle_diff_selection(2);
+        push(&mut state, 8);
+        assert_eq!(stored(&state), vec![1, 6, 7, 8]);
+        assert_eq!(state.selected_for_diff, vec![1]);
+    }
+
+    #[test]
+    fn suspect_counters_follow_the_connection() {
+        use crate::app::suspects::{ExpectedKind, Severity, SuspectRule};
+        let mut state = AppState::default();
+        state.engine.suspect_rules.push(SuspectRule {
+            name: "ff".to_string(),
+            start_index: 0,
+            end_index: 0,
+            expected_kind: ExpectedKind::Hex,
+            expected_value: "FF".to_string(),
+            target: WatchTarget::All,
+            severity: Severity::Warning,
+            trim: false,
+            enabled: true,
+            violation_count: 0,
+            escalate_after: 0,
+            consecutive_violations: 0,
+        });
+        let counters = |state: &AppState| (state.engine.suspect_rules[0].violation_count, state.engine.suspect_rules[0].consecutive_violations);
+        for n in 0..3 {
+            push(&mut state, n);
+        }
+        let mut other = Connection::new(String::new());
+        state.swap_connection(&mut other);
+        assert_eq!(counters(&state), (0, 0));
+        push(&mut state, 3);
+        assert_eq!(counters(&state), (1, 1));
+        state.swap_connection(&mut other);
+        assert_eq!(counters(&state), (3, 3));
+        assert_eq!(other.suspect_runs, vec![(1, 1)]);
+    }
+}
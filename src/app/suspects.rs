@@ -1,21 +1,41 @@
 //! Suspected data rules and evaluation.
+use std::fmt;
 
-use crate::app::state::{parse_hex_bytes, LabelRule, WatchTarget};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::app::checksum::ChecksumAlgo;
+use crate::app::state::{format_index_range, parse_hex_bytes, resolve_range, target_applies, LabelRule, WatchTarget, WHOLE_MESSAGE_END};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExpectedKind {
     Text,
     Hex,
+    /// Comma-separated alternatives, each hex (`01`) or quoted text (`"OK"`).
+    OneOf,
+    /// The checksum of the `over` range must equal the bytes at `stored_at`, in either
+    /// byte order. Both are inclusive ranges as read by `parse_range_field`.
+    Checksum { algo: ChecksumAlgo, over: (usize, usize), stored_at: (usize, usize) },
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl fmt::Display for ExpectedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpectedKind::Text => write!(f, "Text"),
+            ExpectedKind::Hex => write!(f, "Hex"),
+            ExpectedKind::OneOf => write!(f, "One of"),
+            ExpectedKind::Checksum { algo, .. } => write!(f, "Checksum ({})", algo),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Severity {
     Info,
     Warning,
     Critical,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SuspectRule {
     pub name: String,
     pub start_index: usize,
@@ -24,54 +44,175 @@ pub struct SuspectRule {
     pub expected_value: String,
     pub target: WatchTarget,
     pub severity: Severity,
+    /// For `Text` rules, ignore leading/trailing whitespace and NUL padding in the slice.
+    pub trim: bool,
+    /// Disabled rules are kept but skipped during evaluation.
+    pub enabled: bool,
+    /// Received frames that failed this rule since it was created or last reset.
+    #[serde(skip)]
+    pub violation_count: u64,
+    /// Treat violations as Critical once this many arrive in a row; 0 never escalates.
+    pub escalate_after: u64,
+    /// Violations since the rule last passed.
+    #[serde(skip)]
+    pub consecutive_violations: u64,
 }
 
-/// Evaluate suspect rules for a message; return human-readable warnings for non-matches.
-pub fn check_suspects_for_message(
-    message: &[u8],
-    active_label: &Option<String>,
-    rules: &[SuspectRule],
-) -> Vec<(Severity, String)> {
-    let mut warnings = Vec::new();
-    for r in rules {
-        let applies = match (&r.target, active_label) {
-            (WatchTarget::All, _) => true,
-            (WatchTarget::Label(t), Some(lbl)) => t == lbl,
-            (WatchTarget::Label(_), None) => false,
-        };
-        if !applies { continue; }
-        if r.start_index > r.end_index || r.end_index >= message.len() { continue; }
-        let slice = &message[r.start_index..=r.end_index];
-        let ok = match r.expected_kind {
-            ExpectedKind::Text => {
-                let found = String::from_utf8_lossy(slice);
-                found == r.expected_value
+impl SuspectRule {
+    /// `severity`, raised to Critical while the run of consecutive violations is at
+    /// or past `escalate_after`.
+    pub fn effective_severity(&self) -> Severity {
+        if self.escalate_after > 0 && self.consecutive_violations >= self.escalate_after {
+            Severity::Critical
+        } else {
+            self.severity
+        }
+    }
+
+    /// Whether every range the rule reads starts inside a message of `len` bytes.
+    pub fn in_range(&self, len: usize) -> bool {
+        match self.expected_kind {
+            ExpectedKind::Checksum { over, stored_at, .. } => {
+                resolve_range(over.0, over.1, len).is_some() && resolve_range(stored_at.0, stored_at.1, len).is_some()
+            }
+            _ => resolve_range(self.start_index, self.end_index, len).is_some(),
+        }
+    }
+}
+
+/// Parse a `OneOf` value list such as `00, 01, "OK"` into its alternatives.
+pub fn parse_one_of(value: &str) -> Result<Vec<Vec<u8>>, String> {
+    value
+        .split(',')
+        .map(|alt| {
+            let alt = alt.trim();
+            if let Some(text) = alt.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                return Ok(text.as_bytes().to_vec());
+            }
+            let bytes = parse_hex_bytes(alt)?;
+            if bytes.is_empty() {
+                return Err("empty alternative in value list".to_string());
             }
-            ExpectedKind::Hex => {
-                if let Ok(exp) = parse_hex_bytes(&r.expected_value) {
-                    exp.as_slice() == slice
-                } else { false }
+            Ok(bytes)
+        })
+        .collect()
+}
+
+/// Check that an expected value can be compared as `kind`, e.g. that hex parses.
+pub fn validate_expected_value(kind: ExpectedKind, value: &str) -> Result<(), String> {
+    match kind {
+        ExpectedKind::Text => Ok(()),
+        ExpectedKind::Hex => {
+            if parse_hex_bytes(value)?.is_empty() {
+                return Err("expected value is empty".to_string());
             }
-        };
-        if !ok {
-            let got_repr = match r.expected_kind {
-                ExpectedKind::Text => String::from_utf8_lossy(slice).to_string(),
-                ExpectedKind::Hex => format!("0x{}", hex::encode_upper(slice)),
-            };
-            warnings.push((
-                r.severity,
-                format!(
-                    "{}: expected {} at [{}..{}], got {}",
-                    r.name,
-                    match r.expected_kind { ExpectedKind::Text => r.expected_value.clone(), ExpectedKind::Hex => format!("0x{}", r.expected_value) },
-                    r.start_index,
-                    r.end_index,
-                    got_repr
-                ),
-            ));
+            Ok(())
+        }
+        ExpectedKind::OneOf => parse_one_of(value).map(|_| ()),
+        ExpectedKind::Checksum { algo, stored_at: (start, end), .. } => {
+            if end != WHOLE_MESSAGE_END && end - start + 1 != algo.width() {
+                return Err(format!("{} is {} byte(s) but the stored range is {}", algo, algo.width(), end - start + 1));
+            }
+            Ok(())
         }
     }
-    warnings
 }
 
+/// Evaluate one suspect rule against a message; `None` if it passes or doesn't apply.
+///
+/// Violations carry the rule's own severity; `EngineState` escalates runs of them.
+///
+/// A range starting past the end of the message yields an `Info` note rather than
+/// nothing, so a rule that never runs is visible.
+pub fn check_suspect_rule(message: &[u8], active_label: &Option<String>, index: u64, r: &SuspectRule) -> Option<(Severity, String)> {
+    if !r.enabled || !target_applies(&r.target, active_label, index) { return None; }
+    if let ExpectedKind::Checksum { algo, over, stored_at } = r.expected_kind {
+        return check_checksum(message, r, algo, over, stored_at);
+    }
+    let Some((start, end)) = resolve_range(r.start_index, r.end_index, message.len()) else {
+        return Some((
+            Severity::Info,
+            format!("{}: [{}] is out of range (len {})", r.name, format_index_range(r.start_index, r.end_index), message.len()),
+        ));
+    };
+    let slice = &message[start..=end];
+    let ok = match r.expected_kind {
+        ExpectedKind::Text => {
+            let found = String::from_utf8_lossy(slice);
+            if r.trim {
+                found.trim_matches(|c: char| c.is_whitespace() || c == '\0') == r.expected_value
+            } else {
+                found == r.expected_value
+            }
+        }
+        ExpectedKind::Hex => {
+            if let Ok(exp) = parse_hex_bytes(&r.expected_value) {
+                exp.as_slice() == slice
+            } else { false }
+        }
+        ExpectedKind::OneOf => match parse_one_of(&r.expected_value) {
+            Ok(alternatives) => alternatives.iter().any(|alt| alt.as_slice() == slice),
+            Err(_) => false,
+        },
+        ExpectedKind::Checksum { .. } => unreachable!("checked above"),
+    };
+    if ok { return None; }
+    let got_repr = match r.expected_kind {
+        ExpectedKind::Text => String::from_utf8_lossy(slice).to_string(),
+        ExpectedKind::Hex | ExpectedKind::OneOf | ExpectedKind::Checksum { .. } => format!("0x{}", hex::encode_upper(slice)),
+    };
+    Some((
+        r.severity,
+        format!(
+            "{}: expected {} at [{}], got {}",
+            r.name,
+            match r.expected_kind {
+                ExpectedKind::Text => r.expected_value.clone(),
+                ExpectedKind::Hex => format!("0x{}", r.expected_value),
+                ExpectedKind::OneOf => format!("one of {{{}}}", r.expected_value),
+                ExpectedKind::Checksum { .. } => unreachable!("checked above"),
+            },
+            format_index_range(start, end),
+            got_repr
+        ),
+    ))
+}
 
+/// Compare the checksum of `over` with the bytes stored at `stored_at`.
+fn check_checksum(
+    message: &[u8],
+    r: &SuspectRule,
+    algo: ChecksumAlgo,
+    over: (usize, usize),
+    stored_at: (usize, usize),
+) -> Option<(Severity, String)> {
+    let (Some((start, end)), Some((stored_start, stored_end))) = (
+        resolve_range(over.0, over.1, message.len()),
+        resolve_range(stored_at.0, stored_at.1, message.len()),
+    ) else {
+        return Some((
+            Severity::Info,
+            format!(
+                "{}: checksum over [{}] stored at [{}] is out of range (len {})",
+                r.name,
+                format_index_range(over.0, over.1),
+                format_index_range(stored_at.0, stored_at.1),
+                message.len()
+            ),
+        ));
+    };
+    let stored = &message[stored_start..=stored_end];
+    if algo.stored_order(&message[start..=end], stored).is_some() { return None; }
+    Some((
+        r.severity,
+        format!(
+            "{}: {} over [{}] is 0x{}, stored at [{}] is 0x{}",
+            r.name,
+            algo,
+            format_index_range(start, end),
+            algo.compute_hex(&message[start..=end]),
+            format_index_range(stored_start, stored_end),
+            hex::encode_upper(stored)
+        ),
+    ))
+}
@@ -0,0 +1,7 @@
+//! ByteBuster's capture pipeline, usable without the GUI.
+//!
+//! The desktop app in `main.rs` is one consumer of [`app`]; scripts and integration tests
+//! can drive the same framing, labelling, watch and suspect logic through [`process_bytes`].
+pub mod app;
+
+pub use app::engine::{process_bytes, EngineState, FramedResult};
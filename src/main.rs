@@ -1,4 +1,4 @@
-mod app;
+use byte_buster::app;
 use crossbeam_channel::{bounded, select, Receiver, Sender};
 use eframe::egui;
 use log::{error, info};
@@ -6,16 +6,27 @@ use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::thread;
 use std::time::Duration;
-use app::suspects::{ExpectedKind, SuspectRule, check_suspects_for_message};
-use app::state::{AppState, parse_hex_bytes, parse_index_range, format_bytes_for_view, find_message_label, WatchView, WatchTarget, WatchItem, LabelRule, LeftPanelTab};
-use app::net::spawn_connection;
-use app::framing::frame_messages;
+use app::suspects::{ExpectedKind, SuspectRule, validate_expected_value};
+use app::state::{AppState, Connection, parse_hex_bytes, parse_range_field, resolve_range, format_index_range, parse_enum_names, format_enum_names, format_bytes_as_array, format_hex_dump, format_hex_rows, format_hex_ruler, encode_hex, format_escaped_text, format_text, format_control_pictures, HEX_ROW_BYTES, build_label_rule, format_label_condition, DEFAULT_LABEL_COLOR, DisplayMode, DedupScope, FilterMode, message_matches_filter, Endian, Theme, MIN_ZOOM, MAX_ZOOM, MIN_MAX_MESSAGES, MAX_MAX_MESSAGES, WatchView, WatchTarget, WatchItem, LabelRule, LabelConditionForm, LabelMatchMode, LeftPanelTab, target_applies};
+use app::export::{export_messages, export_pcap, ExportFormat, LINKTYPE_USER0};
+use app::net::{connect_in_background, spawn_connection, spawn_stdin_reader, spawn_udp_connection, spawn_serial_connection, available_serial_ports, LinkEvent, LinkHandles, LinkShutdown, Transport, SERIAL_BAUD_RATES, DEFAULT_READ_BUFFER, MIN_READ_BUFFER, MAX_READ_BUFFER};
+use clap::Parser;
+use std::sync::atomic::Ordering;
+use app::recording::{SessionRecorder, SessionReplay};
+use app::import::parse_hex_dump;
+use app::checksum::ChecksumAlgo;
+use app::config::{default_config_path, load_config, save_config, DEFAULT_CONFIG_FILE};
+use app::framing::{flush_partial, FrameStatus, FramingMode, LengthField};
+use app::engine::{process_bytes, FramedResult};
 
 struct ByteBusterApp {
     state: AppState,
     reader_join: Option<thread::JoinHandle<()>>,
     writer_join: Option<thread::JoinHandle<()>>,
-    incoming_buffer: Vec<u8>,
+    /// Stops the network IO threads on disconnect; `None` for stdin capture.
+    link_shutdown: Option<LinkShutdown>,
+    /// When data last arrived, for the idle flush.
+    last_chunk_at: Option<std::time::Instant>,
 }
 
 impl Default for ByteBusterApp {
@@ -24,113 +35,1041 @@ impl Default for ByteBusterApp {
             state: AppState::default(),
             reader_join: None,
             writer_join: None,
-            incoming_buffer: Vec::new(),
+            link_shutdown: None,
+            last_chunk_at: None,
         }
     }
 }
 
-impl eframe::App for ByteBusterApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+/// Most bytes of the unframed buffer shown in its debug view.
+const UNFRAMED_PREVIEW_BYTES: usize = 4096;
+
+/// Storage keys for the layout settings restored on startup.
+const ZOOM_KEY: &str = "zoom";
+const THEME_KEY: &str = "theme";
+const LEFT_TAB_KEY: &str = "left_panel_tab";
+
+impl ByteBusterApp {
+    /// Create the app, restoring the layout saved by the previous session.
+    ///
+    /// Window geometry, panel widths and collapsed sections are restored by eframe itself.
+    fn new(cc: &eframe::CreationContext<'_>, args: &Args) -> Self {
+        let mut app = Self::default();
+        if args.stdin {
+            app.attach_stdin();
+        }
+        if let Some(path) = default_config_path().filter(|p| p.exists()) {
+            match load_config(&path).and_then(|config| config.apply(&mut app.state)) {
+                Ok(()) => info!("loaded config from {}", path.display()),
+                Err(e) => error!("load config failed: {}", e),
+            }
+        }
+        if let Some(storage) = cc.storage {
+            if let Some(zoom) = storage.get_string(ZOOM_KEY).and_then(|z| z.parse::<f32>().ok()) {
+                app.state.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+            match storage.get_string(THEME_KEY).as_deref() {
+                Some("light") => app.state.theme = Theme::Light,
+                Some("dark") => app.state.theme = Theme::Dark,
+                _ => {}
+            }
+            match storage.get_string(LEFT_TAB_KEY).as_deref() {
+                Some("watch") => app.state.left_panel_tab = LeftPanelTab::Watch,
+                Some("labels") => app.state.left_panel_tab = LeftPanelTab::Labels,
+                Some("suspects") => app.state.left_panel_tab = LeftPanelTab::Suspects,
+                _ => {}
+            }
+        }
+        app
+    }
+}
+
+/// Validated fields of the watch edit form, applied after the list is drawn.
+struct WatchEdit {
+    index: usize,
+    name: String,
+    start: usize,
+    end: usize,
+    enum_names: Vec<(u64, String)>,
+}
+
+/// Validated fields of the suspect rule edit form, applied after the list is drawn.
+struct SuspectEdit {
+    index: usize,
+    name: String,
+    start: usize,
+    end: usize,
+    kind: ExpectedKind,
+    value: String,
+    target: WatchTarget,
+    severity: app::suspects::Severity,
+    trim: bool,
+    escalate_after: u64,
+}
+
+/// Status dot color and tooltip for a connection tab.
+fn connection_status(connected: bool, connecting: bool, failed: bool) -> (egui::Color32, &'static str) {
+    if connected {
+        (egui::Color32::from_rgb(0x4c, 0xaf, 0x50), "Connected")
+    } else if connecting {
+        (egui::Color32::from_rgb(0xff, 0xa7, 0x26), "Connecting…")
+    } else if failed {
+        (egui::Color32::RED, "Connect failed")
+    } else {
+        (egui::Color32::GRAY, "Disconnected")
+    }
+}
+
+/// Format a duration as `HH:MM:SS`.
+fn format_uptime(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Format the gap between two messages as `+1.234s`, or `+MM:SS` / `+HH:MM:SS` past a minute.
+fn format_delta(elapsed: Duration) -> String {
+    if elapsed.as_secs() < 60 {
+        format!("+{:.3}s", elapsed.as_secs_f64())
+    } else if elapsed.as_secs() < 3600 {
+        format!("+{:02}:{:02}", elapsed.as_secs() / 60, elapsed.as_secs() % 60)
+    } else {
+        format!("+{}", format_uptime(elapsed))
+    }
+}
+
+/// Send the contents of the Send box.
+const SEND_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Enter);
+/// Clear the message list.
+const CLEAR_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+/// Height assumed for a message card before it has been drawn.
+const ESTIMATED_CARD_HEIGHT: f32 = 80.0;
+/// Connect, or disconnect when connected.
+const CONNECT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+
+impl ByteBusterApp {
+    /// Run a raw chunk through the engine and store any messages it completes.
+    ///
+    /// While paused, completed frames are held back until capture resumes.
+    fn ingest_chunk(&mut self, chunk: &[u8]) {
+        self.last_chunk_at = Some(std::time::Instant::now());
+        for result in process_bytes(&mut self.state.engine, chunk) {
+            self.store_frame(result);
+        }
+    }
+
+    /// Evaluate and store whatever partial frame is buffered; nothing will complete it.
+    fn flush_buffer(&mut self) {
+        if let Some(result) = self.state.engine.flush() {
+            self.store_frame(result);
+        }
+    }
+
+    /// Store an evaluated message, or hold it back while paused.
+    fn store_frame(&mut self, result: FramedResult) {
+        self.state.skipped_bytes += result.skipped;
+        if self.state.paused {
+            self.state.held_frames.push((result, std::time::Instant::now(), chrono::Local::now()));
+        } else {
+            self.state.push_message(result);
+        }
+    }
+
+    /// Start connecting to `address_input`; `poll_connect` finishes the job for TCP.
+    /// UDP sockets and serial ports open at once.
+    fn connect(&mut self) {
+        self.state.connect_error = None;
+        let address = self.state.address_input.clone();
+        let settings = self.state.link_settings.clone();
+        let link = match self.state.transport {
+            Transport::Tcp => {
+                self.state.connecting = Some(connect_in_background(address));
+                return;
+            }
+            Transport::Udp => spawn_udp_connection(address, settings),
+            Transport::Serial => spawn_serial_connection(address, self.state.serial_baud, settings),
+        };
+        match link {
+            Ok(link) => {
+                self.state.peer_addr = None;
+                self.attach_link(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                self.state.connect_error = Some(e);
+            }
+        }
+    }
+
+    /// Take over the IO threads and channels of a link that just came up.
+    fn attach_link(&mut self, (tx, rx, events, shutdown, rj, wj): LinkHandles) {
+        self.state.connected_since = Some(std::time::Instant::now());
+        self.state.tx_to_writer = Some(tx);
+        self.state.rx_from_reader = Some(rx);
+        self.state.link_events = Some(events);
+        self.link_shutdown = Some(shutdown);
+        self.reader_join = Some(rj);
+        self.writer_join = Some(wj);
+        self.state.is_connected = true;
+        info!("connected");
+    }
+
+    /// Abandon a connect attempt that hasn't finished yet.
+    fn cancel_connect(&mut self) {
+        if self.state.connecting.take().is_some() {
+            info!("connect cancelled");
+        }
+    }
+
+    /// Start the IO threads once a background connect succeeds.
+    fn poll_connect(&mut self) {
+        let Some(rx) = &self.state.connecting else { return };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(crossbeam_channel::TryRecvError::Empty) => return,
+            Err(crossbeam_channel::TryRecvError::Disconnected) => Err("connect thread exited".to_string()),
+        };
+        self.state.connecting = None;
+        match result {
+            Ok(stream) => {
+                self.state.peer_addr = stream.peer_addr().ok();
+                let link = spawn_connection(stream, self.state.link_settings.clone());
+                self.attach_link(link);
+            }
+            Err(e) => {
+                error!("{}", e);
+                self.state.connect_error = Some(e);
+            }
+        }
+    }
+
+    /// Capture from stdin in the active tab. There is nothing to send to, so Send stays disabled.
+    fn attach_stdin(&mut self) {
+        let (rx, rj) = spawn_stdin_reader();
+        self.state.address_input = "stdin".to_string();
+        self.state.rx_from_reader = Some(rx);
+        self.reader_join = Some(rj);
+        self.state.connected_since = Some(std::time::Instant::now());
+        self.state.is_connected = true;
+        info!("reading from stdin");
+    }
+
+    /// Drop the connection and stop anything that was sending on it.
+    ///
+    /// Network IO threads are signalled, a TCP socket shut down, and both threads joined. A reader busy in a
+    /// reconnect attempt isn't waited for; it exits once the attempt returns.
+    fn disconnect(&mut self) {
+        let reconnecting = self.state.reconnecting.is_some();
+        let reader_join = self.reader_join.take();
+        let writer_join = self.writer_join.take();
+        if let Some(mut link) = self.link_shutdown.take() {
+            link.shutdown();
+            let handles = if reconnecting { vec![writer_join] } else { vec![reader_join, writer_join] };
+            for handle in handles.into_iter().flatten() {
+                if handle.join().is_err() {
+                    error!("IO thread panicked");
+                }
+            }
+        }
+        self.state.is_connected = false;
+        self.state.peer_addr = None;
+        self.state.connected_since = None;
+        self.state.repeat_payload = None;
+        self.state.file_transfer = None;
+        self.state.tx_to_writer = None;
+        self.state.rx_from_reader = None;
+        self.state.link_events = None;
+        self.state.reconnecting = None;
+        // Nothing will complete the partial frame now
+        self.flush_buffer();
+    }
+
+    /// Send the Send box contents and remember them in the history.
+    fn send_input(&mut self) {
+        if self.state.tx_to_writer.is_none() { return; }
+        let input = self.state.send_hex_input.clone();
+        match self.state.send_hex(&input) {
+            Ok(_) => self.state.record_send(&input),
+            Err(e) => { error!("send parse error: {}", e); }
+        }
+    }
+
+    /// Run the selected connection: finish a pending connect, frame received data and
+    /// drive repeating and file sends. Only the `record` pass feeds an active recording.
+    fn pump_connection(&mut self, ctx: &egui::Context, record: bool) {
+        self.poll_connect();
+        if self.state.connecting.is_some() {
+            // Keep polling even when no input arrives
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
         // Pump incoming data
-        if let Some(rx) = &self.state.rx_from_reader {
+        if let Some(rx) = self.state.rx_from_reader.clone() {
             loop {
                 match rx.try_recv() {
                     Ok(chunk) => {
-                        self.incoming_buffer.extend_from_slice(&chunk);
-                        // framing
-                        let start = parse_hex_bytes(&self.state.start_pattern).unwrap_or_default();
-                        let end = parse_hex_bytes(&self.state.end_pattern).unwrap_or_default();
-                        for msg in frame_messages(&mut self.incoming_buffer, &start, &end) {
-                            self.state.received_messages.push(msg);
-                            if self.state.received_messages.len() > self.state.max_messages {
-                                let overflow = self.state.received_messages.len() - self.state.max_messages;
-                                self.state.received_messages.drain(0..overflow);
+                        if let Some(recorder) = self.state.recorder.as_mut().filter(|_| record) {
+                            if let Err(e) = recorder.record(&chunk) {
+                                error!("recording to {} failed: {}", recorder.name, e);
+                                self.state.recorder = None;
                             }
                         }
+                        self.ingest_chunk(&chunk);
+                        // A frame never continues into the next datagram
+                        if self.state.transport == Transport::Udp {
+                            self.flush_buffer();
+                        }
                     }
                     Err(_) => break,
                 }
             }
         }
 
+        // Reconnect progress; a peer that closes for good ends the session
+        let events: Vec<LinkEvent> = self.state.link_events.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default();
+        for event in events {
+            match event {
+                LinkEvent::Reconnecting { attempt, .. } => self.state.reconnecting = Some(attempt),
+                LinkEvent::Reconnected => {
+                    self.state.reconnecting = None;
+                    self.state.connected_since = Some(std::time::Instant::now());
+                }
+                LinkEvent::Closed => {
+                    info!("connection closed by peer");
+                    self.disconnect();
+                    return;
+                }
+            }
+        }
+        if self.state.reconnecting.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+
+        // Idle flush: give up waiting for the rest of a partial frame
+        if self.state.idle_flush_ms > 0 && !self.state.engine.buffer.is_empty() {
+            let idle = Duration::from_millis(self.state.idle_flush_ms);
+            let elapsed = self.last_chunk_at.map_or(idle, |t| t.elapsed());
+            if elapsed >= idle {
+                self.flush_buffer();
+            } else {
+                ctx.request_repaint_after(idle - elapsed);
+            }
+        }
+
+        // Repeating send: fire on schedule and keep repainting so it runs while unfocused
+        if let Some(payload) = self.state.repeat_payload.clone() {
+            let interval = Duration::from_millis(self.state.repeat_interval_ms.max(1));
+            let elapsed = self.state.last_repeat.elapsed();
+            if elapsed >= interval {
+                // A full queue is counted in `send_dropped`; the next repeat tries again
+                let _ = self.state.queue_send(payload);
+                self.state.last_repeat = std::time::Instant::now();
+                ctx.request_repaint_after(interval);
+            } else {
+                ctx.request_repaint_after(interval - elapsed);
+            }
+        }
+
+        // File send: queue the next chunk once the inter-chunk delay has passed
+        if let (Some(transfer), Some(tx)) = (&mut self.state.file_transfer, &self.state.tx_to_writer) {
+            let delay = Duration::from_millis(self.state.file_chunk_delay_ms);
+            if transfer.last_chunk.elapsed() >= delay {
+                let chunk = if self.state.file_chunk_size == 0 { transfer.data.len() } else { self.state.file_chunk_size };
+                let end = (transfer.sent + chunk).min(transfer.data.len());
+                // On a full queue keep the position and retry after the next delay
+                if tx.try_send(transfer.data[transfer.sent..end].to_vec()).is_ok() {
+                    transfer.sent = end;
+                }
+                transfer.last_chunk = std::time::Instant::now();
+            }
+            ctx.request_repaint_after(delay);
+        }
+        if self.state.file_transfer.as_ref().is_some_and(|t| t.sent >= t.data.len()) {
+            if let Some(t) = self.state.file_transfer.take() {
+                info!("sent file {} ({} bytes)", t.name, t.data.len());
+            }
+        }
+    }
+
+    /// Exchange the selected connection with a parked one, including the app-held IO state.
+    fn swap_connection(&mut self, other: &mut Connection) {
+        self.state.swap_connection(other);
+        std::mem::swap(&mut self.last_chunk_at, &mut other.last_chunk_at);
+        std::mem::swap(&mut self.reader_join, &mut other.reader_join);
+        std::mem::swap(&mut self.writer_join, &mut other.writer_join);
+        std::mem::swap(&mut self.link_shutdown, &mut other.link_shutdown);
+    }
+
+    /// Switch to connection tab `index`.
+    fn select_connection(&mut self, index: usize) {
+        if index == self.state.active_connection || index >= self.state.connections.len() { return; }
+        let mut parked = std::mem::replace(&mut self.state.connections[index], Connection::new(String::new()));
+        self.swap_connection(&mut parked);
+        self.state.connections[self.state.active_connection] = parked;
+        self.state.active_connection = index;
+        // Note drafts are keyed by sequence index, which each tab counts separately
+        self.state.note_editing = None;
+    }
+
+    /// Open a new, disconnected tab and switch to it.
+    fn add_connection(&mut self) {
+        self.state.connections.push(Connection::new(self.state.address_input.clone()));
+        self.select_connection(self.state.connections.len() - 1);
+    }
+
+    /// Close tab `index`, dropping its connection and messages; the last tab can't be closed.
+    fn close_connection(&mut self, index: usize) {
+        if self.state.connections.len() <= 1 || index >= self.state.connections.len() { return; }
+        let previous = self.state.active_connection;
+        // Bring the tab in so its IO threads are stopped like on Disconnect
+        self.select_connection(index);
+        self.disconnect();
+        self.select_connection(if previous != index { previous } else if index > 0 { index - 1 } else { 1 });
+        self.state.connections.remove(index);
+        if self.state.active_connection > index {
+            self.state.active_connection -= 1;
+        }
+    }
+
+    /// Clear all messages along with the partial frame and per-capture state.
+    fn clear_messages(&mut self) {
+        self.state.received_messages.clear();
+        self.state.held_frames.clear();
+        self.state.engine.buffer.clear();
+        self.state.critical_messages = 0;
+        self.state.skipped_bytes = 0;
+        self.state.dropped_messages = 0;
+        self.state.engine.next_index = 0;
+        self.state.selected_for_diff.clear();
+        self.state.label_stats.clear();
+    }
+}
+
+impl eframe::App for ByteBusterApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(ZOOM_KEY, self.state.zoom.to_string());
+        let theme = match self.state.theme {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        };
+        storage.set_string(THEME_KEY, theme.to_string());
+        let tab = match self.state.left_panel_tab {
+            LeftPanelTab::Watch => "watch",
+            LeftPanelTab::Labels => "labels",
+            LeftPanelTab::Suspects => "suspects",
+        };
+        storage.set_string(LEFT_TAB_KEY, tab.to_string());
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.state.refresh_delimiters();
+
+        // Background tabs keep receiving and sending; swap each one in to run the same path
+        for i in 0..self.state.connections.len() {
+            if i == self.state.active_connection { continue; }
+            let mut parked = std::mem::replace(&mut self.state.connections[i], Connection::new(String::new()));
+            self.swap_connection(&mut parked);
+            self.pump_connection(ctx, false);
+            self.swap_connection(&mut parked);
+            self.state.connections[i] = parked;
+        }
+        self.pump_connection(ctx, true);
+
+        // Replay: feed recorded chunks through the same framing path on their original schedule
+        if let Some(replay) = &mut self.state.replay {
+            let due = replay.due_chunks();
+            let next = replay.time_to_next();
+            for chunk in due {
+                self.ingest_chunk(&chunk);
+            }
+            match next {
+                Some(wait) => ctx.request_repaint_after(wait),
+                None => {
+                    if let Some(r) = self.state.replay.take() {
+                        info!("replay of {} finished", r.name);
+                    }
+                }
+            }
+        }
+
+        // Zoom: handle Ctrl+Plus/Minus/0 ourselves so the configured range applies
+        ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        ctx.input_mut(|i| {
+            use egui::gui_zoom::kb_shortcuts;
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_RESET) {
+                self.state.zoom = 1.0;
+            }
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_IN) || i.consume_shortcut(&kb_shortcuts::ZOOM_IN_SECONDARY) {
+                self.state.zoom += 0.1;
+            }
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_OUT) {
+                self.state.zoom -= 0.1;
+            }
+        });
+        self.state.zoom = self.state.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+
+        // Keyboard shortcuts; Space only pauses when no text field has focus
+        let (send, clear, toggle_connection) = ctx.input_mut(|i| {
+            (i.consume_shortcut(&SEND_SHORTCUT), i.consume_shortcut(&CLEAR_SHORTCUT), i.consume_shortcut(&CONNECT_SHORTCUT))
+        });
+        let toggle_pause = !ctx.wants_keyboard_input() && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Space));
+        if send { self.send_input(); }
+        if clear { self.clear_messages(); }
+        if toggle_connection {
+            if self.state.is_connected {
+                self.disconnect();
+            } else if self.state.connecting.is_some() {
+                self.cancel_connect();
+            } else {
+                self.connect();
+            }
+        }
+        if toggle_pause {
+            let paused = !self.state.paused;
+            self.state.set_paused(paused);
+        }
+        if (ctx.zoom_factor() - self.state.zoom).abs() > f32::EPSILON {
+            ctx.set_zoom_factor(self.state.zoom);
+        }
+
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             // Apply a base theme and tint the panels if a critical is active
-            let mut visuals = egui::Visuals::dark();
-            if self.state.critical_active {
-                visuals.panel_fill = egui::Color32::from_rgb(60, 20, 20);
+            let mut visuals = match self.state.theme {
+                Theme::Dark => egui::Visuals::dark(),
+                Theme::Light => egui::Visuals::light(),
+            };
+            if self.state.critical_messages > 0 {
+                visuals.panel_fill = match self.state.theme {
+                    Theme::Dark => egui::Color32::from_rgb(60, 20, 20),
+                    Theme::Light => egui::Color32::from_rgb(255, 215, 215),
+                };
             }
             ctx.set_visuals(visuals);
-            ui.heading("ByteBuster");
             ui.horizontal(|ui| {
-                ui.label("Address");
-                ui.text_edit_singleline(&mut self.state.address_input);
-                if !self.state.is_connected {
-                    if ui.button("Connect").clicked() {
-                        match std::panic::catch_unwind({
-                            let addr = self.state.address_input.clone();
-                            move || spawn_connection(addr)
-                        }) {
-                            Ok((tx, rx, rj, wj)) => {
-                                self.state.tx_to_writer = Some(tx);
-                                self.state.rx_from_reader = Some(rx);
-                                self.reader_join = Some(rj);
-                                self.writer_join = Some(wj);
-                                self.state.is_connected = true;
-                                info!("connected");
-                            }
-                            Err(_) => {
-                                error!("connect panic");
+                ui.heading("ByteBuster");
+                ui.separator();
+                let mut to_select: Option<usize> = None;
+                let mut to_close: Option<usize> = None;
+                let can_close = self.state.connections.len() > 1;
+                for (i, conn) in self.state.connections.iter().enumerate() {
+                    let active = i == self.state.active_connection;
+                    let (address, connected, connecting, failed) = if active {
+                        (&self.state.address_input, self.state.is_connected, self.state.connecting.is_some(), self.state.connect_error.is_some())
+                    } else {
+                        (&conn.address_input, conn.is_connected, conn.connecting.is_some(), conn.connect_error.is_some())
+                    };
+                    let (dot, status) = connection_status(connected, connecting, failed);
+                    ui.colored_label(dot, "●").on_hover_text(status);
+                    if ui.selectable_label(active, address).clicked() { to_select = Some(i); }
+                    if can_close && ui.small_button("×").on_hover_text("Close this connection").clicked() { to_close = Some(i); }
+                }
+                if ui.small_button("+").on_hover_text("New connection tab").clicked() {
+                    self.add_connection();
+                }
+                if let Some(i) = to_select { self.select_connection(i); }
+                if let Some(i) = to_close { self.close_connection(i); }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.selectable_value(&mut self.state.theme, Theme::Light, "Light");
+                    ui.selectable_value(&mut self.state.theme, Theme::Dark, "Dark");
+                    ui.label("Theme");
+                    ui.separator();
+                    ui.add(egui::Slider::new(&mut self.state.zoom, MIN_ZOOM..=MAX_ZOOM).step_by(0.05).text("Zoom"))
+                        .on_hover_text("Ctrl+Plus / Ctrl+Minus, Ctrl+0 to reset");
+                });
+            });
+            ui.horizontal(|ui| {
+                let idle = !self.state.is_connected && self.state.connecting.is_none();
+                ui.add_enabled_ui(idle, |ui| {
+                    egui::ComboBox::from_id_source("transport")
+                        .width(60.0)
+                        .selected_text(self.state.transport.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.state.transport, Transport::Tcp, Transport::Tcp.to_string());
+                            ui.selectable_value(&mut self.state.transport, Transport::Udp, Transport::Udp.to_string());
+                            if ui.selectable_value(&mut self.state.transport, Transport::Serial, Transport::Serial.to_string()).clicked() {
+                                self.state.serial_ports = available_serial_ports();
                             }
+                        });
+                    if self.state.transport == Transport::Serial {
+                        ui.label("Port");
+                        egui::ComboBox::from_id_source("serial_port")
+                            .width(140.0)
+                            .selected_text(self.state.address_input.clone())
+                            .show_ui(ui, |ui| {
+                                if self.state.serial_ports.is_empty() {
+                                    ui.weak("No ports found");
+                                }
+                                for port in &self.state.serial_ports {
+                                    ui.selectable_value(&mut self.state.address_input, port.clone(), port);
+                                }
+                            });
+                        if ui.small_button("⟳").on_hover_text("Scan for serial ports").clicked() {
+                            self.state.serial_ports = available_serial_ports();
                         }
+                        ui.label("Baud");
+                        egui::ComboBox::from_id_source("serial_baud")
+                            .width(80.0)
+                            .selected_text(self.state.serial_baud.to_string())
+                            .show_ui(ui, |ui| {
+                                for baud in SERIAL_BAUD_RATES {
+                                    ui.selectable_value(&mut self.state.serial_baud, baud, baud.to_string());
+                                }
+                            });
+                    }
+                });
+                if self.state.transport != Transport::Serial {
+                    ui.label("Address");
+                    let address = ui.text_edit_singleline(&mut self.state.address_input);
+                    if self.state.transport == Transport::Udp {
+                        address.on_hover_text("host:port sends to and receives from that peer; :port or 0.0.0.0:port listens on a local port and replies to the latest sender");
+                    }
+                }
+                let connect_hint = format!("Connect / disconnect ({})", ctx.format_shortcut(&CONNECT_SHORTCUT));
+                if self.state.is_connected {
+                    if ui.button("Disconnect").on_hover_text(connect_hint).clicked() {
+                        self.disconnect();
+                    }
+                    if let Some(attempt) = self.state.reconnecting {
+                        ui.spinner();
+                        ui.label(format!("Reconnecting… (attempt {})", attempt));
+                    } else if let Some(since) = self.state.connected_since {
+                        let peer = self.state.peer_addr.map_or_else(|| self.state.address_input.clone(), |a| a.to_string());
+                        ui.label(format!("Connected to {} · up {}", peer, format_uptime(since.elapsed())));
+                        ctx.request_repaint_after(Duration::from_secs(1));
+                    }
+                } else if self.state.connecting.is_some() {
+                    ui.spinner();
+                    ui.label("Connecting…");
+                    if ui.button("Cancel").on_hover_text(connect_hint).clicked() {
+                        self.cancel_connect();
                     }
                 } else {
-                    if ui.button("Disconnect").clicked() {
-                        self.state.is_connected = false;
-                        self.state.tx_to_writer = None;
-                        self.state.rx_from_reader = None;
-                        self.reader_join.take();
-                        self.writer_join.take();
+                    if ui.button("Connect").on_hover_text(connect_hint).clicked() {
+                        self.connect();
+                    }
+                    if let Some(e) = &self.state.connect_error {
+                        ui.colored_label(ui.visuals().error_fg_color, "Connect failed").on_hover_text(e);
                     }
                 }
+                let mut send_limit = self.state.link_settings.send_bytes_per_sec.load(Ordering::Relaxed);
+                ui.label("Send limit");
+                let limit_text = if send_limit == 0 { "unlimited".to_string() } else { format!("{} B/s", send_limit) };
+                if ui.add(egui::DragValue::new(&mut send_limit).speed(10.0).clamp_range(0..=10_000_000).custom_formatter(move |_, _| limit_text.clone()))
+                    .on_hover_text("Maximum outgoing bytes per second; 0 is unlimited")
+                    .changed()
+                {
+                    self.state.link_settings.send_bytes_per_sec.store(send_limit, Ordering::Relaxed);
+                }
 
                 ui.separator();
                 ui.label("Send");
-                let reserve_for_button = 90.0; // approximate width for the button
+                let reserve_for_button = 120.0; // approximate width for the history and send buttons
                 let available = ui.available_width();
                 let input_width = (available - reserve_for_button).max(120.0);
                 let row_h = ui.spacing().interact_size.y; // match button height
-                ui.add_sized(
-                    [input_width, row_h],
-                    egui::TextEdit::singleline(&mut self.state.send_hex_input)
-                        .hint_text("hex bytes (e.g. FE ED FA CE)"),
+                // Up/Down recall history while the field is focused; consume them before the TextEdit sees them
+                let send_input_id = egui::Id::new("send_hex_input");
+                if ui.memory(|m| m.has_focus(send_input_id)) {
+                    let (up, down) = ui.input_mut(|i| {
+                        (i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp), i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown))
+                    });
+                    if up { self.state.recall_send_history(true); }
+                    if down { self.state.recall_send_history(false); }
+                }
+                let parsed = parse_hex_bytes(&self.state.send_hex_input);
+                let mut send_edit = egui::TextEdit::singleline(&mut self.state.send_hex_input)
+                    .id(send_input_id)
+                    .hint_text("hex bytes (e.g. FE ED FA CE)");
+                if parsed.is_err() {
+                    send_edit = send_edit.text_color(ui.visuals().error_fg_color);
+                }
+                let send_response = ui.add_sized([input_width, row_h], send_edit);
+                if parsed.is_err() {
+                    ui.painter().rect_stroke(send_response.rect, 2.0, egui::Stroke::new(1.0, ui.visuals().error_fg_color));
+                }
+                ui.menu_button("⏷", |ui| {
+                    if self.state.send_history.is_empty() {
+                        ui.label("No recent sends");
+                    }
+                    for entry in self.state.send_history.iter().rev() {
+                        if ui.button(egui::RichText::new(entry).monospace()).clicked() {
+                            self.state.send_hex_input = entry.clone();
+                            ui.close_menu();
+                        }
+                    }
+                }).response.on_hover_text("Recent sends (Up/Down in the field)");
+                let can_send = self.state.tx_to_writer.is_some() && matches!(&parsed, Ok(bytes) if !bytes.is_empty());
+                if ui.add_enabled(can_send, egui::Button::new("Send")).on_hover_text(ctx.format_shortcut(&SEND_SHORTCUT)).clicked() {
+                    self.send_input();
+                }
+                match &parsed {
+                    Ok(bytes) if !bytes.is_empty() => { ui.weak(format!("{} bytes", bytes.len())); }
+                    Ok(_) => {}
+                    Err(e) => { ui.colored_label(ui.visuals().error_fg_color, e); }
+                }
+                if self.state.tx_to_writer.as_ref().is_some_and(|tx| tx.is_full()) {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Send buffer full")
+                        .on_hover_text("The writer can't keep up; the peer may have stopped reading");
+                }
+                if self.state.send_dropped > 0 {
+                    let dropped = ui.colored_label(ui.visuals().warn_fg_color, format!("{} sends dropped", self.state.send_dropped))
+                        .on_hover_text("Sends discarded while the send buffer was full; click to reset");
+                    if dropped.interact(egui::Sense::click()).clicked() {
+                        self.state.send_dropped = 0;
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.state.prepend_start_delimiter, "Prepend start delimiter")
+                    .on_hover_text("Put the first start pattern in front of every send");
+                ui.checkbox(&mut self.state.append_end_delimiter, "Append end delimiter")
+                    .on_hover_text("Add the end pattern (e.g. 0D 0A) after every send");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Repeat every");
+                ui.add_enabled(
+                    self.state.repeat_payload.is_none(),
+                    egui::DragValue::new(&mut self.state.repeat_interval_ms).clamp_range(10..=3_600_000).suffix(" ms"),
                 );
-                if ui.button("Send").clicked() {
-                    if let Some(tx) = &self.state.tx_to_writer {
-                        match parse_hex_bytes(&self.state.send_hex_input) {
-                            Ok(bytes) => { let _ = tx.send(bytes); }
-                            Err(e) => { error!("send parse error: {}", e); }
+                match &self.state.repeat_payload {
+                    Some(payload) => {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, format!("Sending {} bytes every {}ms", payload.len(), self.state.repeat_interval_ms));
+                        if ui.button("Stop").clicked() {
+                            self.state.repeat_payload = None;
+                        }
+                    }
+                    None => {
+                        let can_start = self.state.tx_to_writer.is_some();
+                        if ui.add_enabled(can_start, egui::Button::new("Start repeat")).on_hover_text("Repeatedly send the Send box contents").clicked() {
+                            match parse_hex_bytes(&self.state.send_hex_input) {
+                                Ok(bytes) => {
+                                    self.state.repeat_payload = Some(self.state.frame_outgoing(bytes));
+                                    // Backdate so the first send happens immediately
+                                    let now = std::time::Instant::now();
+                                    self.state.last_repeat = now.checked_sub(Duration::from_millis(self.state.repeat_interval_ms)).unwrap_or(now);
+                                }
+                                Err(e) => error!("repeat parse error: {}", e),
+                            }
+                        }
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                let idle = self.state.file_transfer.is_none();
+                if ui.add_enabled(idle && self.state.tx_to_writer.is_some(), egui::Button::new("Send file…")).clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        match std::fs::read(&path) {
+                            Ok(data) => {
+                                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                                self.state.start_chunked_send(name, data);
+                            }
+                            Err(e) => error!("read {} failed: {}", path.display(), e),
+                        }
+                    }
+                }
+                ui.label("Chunk");
+                ui.add_enabled(idle, egui::DragValue::new(&mut self.state.file_chunk_size).clamp_range(0..=1_048_576).suffix(" B"))
+                    .on_hover_text("0 sends the whole file in one write");
+                ui.label("Delay");
+                ui.add_enabled(idle, egui::DragValue::new(&mut self.state.file_chunk_delay_ms).clamp_range(0..=10_000).suffix(" ms"));
+                ui.checkbox(&mut self.state.chunk_large_sends, "Chunk large sends")
+                    .on_hover_text("Send box payloads bigger than one chunk are split and paced the same way");
+                let mut cancel = false;
+                if let Some(t) = &self.state.file_transfer {
+                    let progress = if t.data.is_empty() { 1.0 } else { t.sent as f32 / t.data.len() as f32 };
+                    ui.add(egui::ProgressBar::new(progress).desired_width(160.0).text(format!("{} {}/{} B", t.name, t.sent, t.data.len())));
+                    cancel = ui.button("Cancel").clicked();
+                }
+                if cancel {
+                    self.state.file_transfer = None;
+                }
+            });
+            ui.horizontal(|ui| {
+                if let Some(recorder) = &self.state.recorder {
+                    ui.colored_label(egui::Color32::LIGHT_RED, format!("● Recording {} ({} chunks, {} B)", recorder.name, recorder.chunks, recorder.bytes));
+                    if ui.button("Stop recording").clicked() {
+                        if let Some(recorder) = self.state.recorder.take() {
+                            if let Err(e) = recorder.finish() {
+                                error!("finishing recording failed: {}", e);
+                            }
+                        }
+                    }
+                } else if ui.button("Record…").on_hover_text("Save every received chunk with its timing").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().set_file_name("session.bbrec").save_file() {
+                        match SessionRecorder::create(&path) {
+                            Ok(recorder) => self.state.recorder = Some(recorder),
+                            Err(e) => error!("create {} failed: {}", path.display(), e),
+                        }
+                    }
+                }
+                ui.separator();
+                if let Some(replay) = &self.state.replay {
+                    let (done, total) = replay.progress();
+                    let progress = if total == 0 { 1.0 } else { done as f32 / total as f32 };
+                    ui.add(egui::ProgressBar::new(progress).desired_width(160.0).text(format!("{} {}/{} chunks", replay.name, done, total)));
+                    if ui.button("Stop replay").clicked() {
+                        self.state.replay = None;
+                    }
+                } else if ui.button("Replay…").on_hover_text("Feed a recorded session through framing, watches and suspects").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        match SessionReplay::load(&path) {
+                            Ok(replay) => self.state.replay = Some(replay),
+                            Err(e) => error!("load {} failed: {}", path.display(), e),
                         }
                     }
                 }
             });
+            if !self.state.send_presets.is_empty() {
+                let mut to_send: Option<usize> = None;
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Presets");
+                    for (i, (name, hex_str)) in self.state.send_presets.iter().enumerate() {
+                        let button = ui.add_enabled(self.state.tx_to_writer.is_some(), egui::Button::new(name.as_str()));
+                        if button.on_hover_text(hex_str.as_str()).clicked() {
+                            to_send = Some(i);
+                        }
+                    }
+                });
+                if let Some((name, hex_str)) = to_send.and_then(|i| self.state.send_presets.get(i).cloned()) {
+                    if let Err(e) = self.state.send_hex(&hex_str) {
+                        error!("preset '{}' send error: {}", name, e);
+                    }
+                }
+            }
         });
 
         egui::SidePanel::left("left").show(ctx, |ui| {
             ui.collapsing("Framing", |ui| {
-                ui.label("Start bytes (hex, space-separated)");
+                ui.horizontal(|ui| {
+                    let what = if self.state.start_pattern_ascii { "text" } else { "hex, space-separated" };
+                    ui.label(format!("Start bytes ({})", what))
+                        .on_hover_text("Separate alternative start delimiters with commas, e.g. AA 55, 5A A5");
+                    pattern_mode_toggle(ui, &mut self.state.start_pattern_ascii);
+                });
                 ui.text_edit_singleline(&mut self.state.start_pattern);
-                ui.label("End bytes (hex, space-separated)");
-                ui.text_edit_singleline(&mut self.state.end_pattern);
+                ui.add_enabled_ui(self.state.engine.framing_mode == FramingMode::Delimited, |ui| {
+                    ui.horizontal(|ui| {
+                        let what = if self.state.end_pattern_ascii { "text" } else { "hex, space-separated" };
+                        ui.label(format!("End bytes ({})", what));
+                        pattern_mode_toggle(ui, &mut self.state.end_pattern_ascii);
+                    });
+                    ui.text_edit_singleline(&mut self.state.end_pattern);
+                    if let Some(warning) = &self.state.delimiter_warning {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Frame on");
+                    ui.radio_value(&mut self.state.engine.framing_mode, FramingMode::Delimited, FramingMode::Delimited.to_string());
+                    ui.radio_value(&mut self.state.engine.framing_mode, FramingMode::StartDelimited, FramingMode::StartDelimited.to_string())
+                        .on_hover_text("For sync-word-only protocols: each message runs up to the next start pattern");
+                    let length_prefixed = matches!(self.state.engine.framing_mode, FramingMode::LengthPrefixed(_));
+                    if ui.radio(length_prefixed, "Length prefix")
+                        .on_hover_text("For binary protocols: a length field after the start pattern gives the frame size")
+                        .clicked()
+                        && !length_prefixed
+                    {
+                        self.state.engine.framing_mode = FramingMode::LengthPrefixed(LengthField::default());
+                    }
+                });
+                if let FramingMode::LengthPrefixed(field) = &mut self.state.engine.framing_mode {
+                    ui.horizontal(|ui| {
+                        ui.label("Length at byte");
+                        ui.add(egui::DragValue::new(&mut field.offset).clamp_range(0..=1024))
+                            .on_hover_text("Offset of the length field from the first start byte");
+                        ui.label("size");
+                        ui.radio_value(&mut field.size, 1, "1");
+                        ui.radio_value(&mut field.size, 2, "2");
+                        ui.radio_value(&mut field.size, 4, "4");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut field.endian, Endian::Big, "BE");
+                        ui.radio_value(&mut field.endian, Endian::Little, "LE");
+                        ui.checkbox(&mut field.includes_header, "Length includes header")
+                            .on_hover_text("Checked: the length is the whole frame. Unchecked: only the bytes after the length field");
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Idle flush");
+                    ui.add(egui::DragValue::new(&mut self.state.idle_flush_ms).clamp_range(0..=60_000).suffix(" ms"))
+                        .on_hover_text("Store a partial frame once no data has arrived for this long; 0 is off. Needed for the last record in start-only framing");
+                });
                 ui.horizontal(|ui| {
                     ui.label("Unit size");
-                    ui.radio_value(&mut self.state.unit_size, 1, "1");
-                    ui.radio_value(&mut self.state.unit_size, 2, "2");
-                    ui.radio_value(&mut self.state.unit_size, 4, "4");
+                    ui.radio_value(&mut self.state.engine.unit_size, 1, "1");
+                    ui.radio_value(&mut self.state.engine.unit_size, 2, "2");
+                    ui.radio_value(&mut self.state.engine.unit_size, 4, "4");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Simulate slow link");
+                    let mut delay = self.state.link_settings.read_delay_ms_per_byte.load(Ordering::Relaxed);
+                    if ui.add(egui::DragValue::new(&mut delay).clamp_range(0..=1000).suffix(" ms/byte"))
+                        .on_hover_text("Deliver received bytes one at a time with this delay; 0 is off")
+                        .changed()
+                    {
+                        self.state.link_settings.read_delay_ms_per_byte.store(delay, Ordering::Relaxed);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Read buffer");
+                    let mut size = self.state.link_settings.read_buffer_size.load(Ordering::Relaxed);
+                    if ui.add(egui::DragValue::new(&mut size).clamp_range(MIN_READ_BUFFER..=MAX_READ_BUFFER).suffix(" bytes"))
+                        .on_hover_text(format!("Most bytes taken per read: small shows data sooner, large suits bulk captures (default {})", DEFAULT_READ_BUFFER))
+                        .changed()
+                    {
+                        self.state.link_settings.read_buffer_size.store(size, Ordering::Relaxed);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let mut auto = self.state.link_settings.auto_reconnect.load(Ordering::Relaxed);
+                    if ui.checkbox(&mut auto, "Auto-reconnect")
+                        .on_hover_text("Re-dial with exponential backoff when the peer drops the connection; queued sends wait for it")
+                        .changed()
+                    {
+                        self.state.link_settings.auto_reconnect.store(auto, Ordering::Relaxed);
+                    }
+                    let mut max_secs = self.state.link_settings.max_backoff_ms.load(Ordering::Relaxed) / 1000;
+                    ui.label("max wait");
+                    if ui.add_enabled(auto, egui::DragValue::new(&mut max_secs).clamp_range(1..=3600).suffix(" s"))
+                        .on_hover_text("Longest pause between reconnect attempts")
+                        .changed()
+                    {
+                        self.state.link_settings.max_backoff_ms.store(max_secs * 1000, Ordering::Relaxed);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Default byte order");
+                    let little = ui.radio_value(&mut self.state.default_endian, Endian::Little, "LE");
+                    let big = ui.radio_value(&mut self.state.default_endian, Endian::Big, "BE");
+                    if little.changed() || big.changed() {
+                        self.state.new_watch_endian = self.state.default_endian;
+                    }
+                });
+            });
+
+            ui.collapsing("Settings", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Keep last");
+                    if ui.add(egui::DragValue::new(&mut self.state.max_messages)
+                        .clamp_range(MIN_MAX_MESSAGES..=MAX_MAX_MESSAGES)
+                        .speed(10)
+                        .suffix(" messages"))
+                        .on_hover_text("Older unpinned messages are dropped once the list is longer than this")
+                        .changed()
+                    {
+                        self.state.trim_messages();
+                    }
+                });
+                ui.checkbox(&mut self.state.hex_uppercase, "Uppercase hex")
+                    .on_hover_text("Show hex as AA55 instead of aa55");
+                ui.checkbox(&mut self.state.show_hex_ruler, "Index ruler")
+                    .on_hover_text("Show byte offsets above hex message bodies");
+                ui.checkbox(&mut self.state.escape_text, "Escape non-text bytes")
+                    .on_hover_text("Show invalid UTF-8 and control bytes as \\xNN in text views instead of �");
+                ui.horizontal(|ui| {
+                    ui.label("Hexdump row");
+                    ui.add(egui::DragValue::new(&mut self.state.hexdump_width).clamp_range(4..=64).suffix(" bytes"))
+                        .on_hover_text("Match your record size so fields line up in columns");
+                });
+            });
+
+            ui.collapsing("Send presets", |ui| {
+                let mut to_delete: Option<usize> = None;
+                for (i, (name, hex_str)) in self.state.send_presets.iter_mut().enumerate() {
+                    ui.push_id(("send_preset", i), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(name).desired_width(80.0).hint_text("name"));
+                            ui.add(egui::TextEdit::singleline(hex_str).desired_width(120.0).hint_text("hex"));
+                            if ui.small_button("Delete").clicked() { to_delete = Some(i); }
+                        });
+                    });
+                }
+                if let Some(i) = to_delete {
+                    self.state.send_presets.remove(i);
+                }
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.state.new_preset_name).desired_width(80.0).hint_text("e.g. Ping"));
+                    ui.add(egui::TextEdit::singleline(&mut self.state.new_preset_hex).desired_width(120.0).hint_text("e.g. FE ED FA CE"));
+                    if ui.small_button("Add").clicked() {
+                        match parse_hex_bytes(&self.state.new_preset_hex) {
+                            Ok(_) => {
+                                let name = std::mem::take(&mut self.state.new_preset_name);
+                                let hex_str = std::mem::take(&mut self.state.new_preset_hex);
+                                self.state.send_presets.push((name, hex_str));
+                            }
+                            Err(e) => error!("preset parse error: {}", e),
+                        }
+                    }
+                });
+            });
+
+            ui.collapsing("CRC calculator", |ui| {
+                ui.label("Frame hex");
+                ui.add(egui::TextEdit::multiline(&mut self.state.crc_calc_hex).desired_rows(2).hint_text("paste a known-good frame"));
+                ui.label("Checksummed range");
+                ui.text_edit_singleline(&mut self.state.crc_calc_range)
+                    .on_hover_text("e.g. 1-6; blank checksums the whole frame");
+                let frame = match parse_hex_bytes(&self.state.crc_calc_hex) {
+                    Ok(frame) if !frame.is_empty() => frame,
+                    Ok(_) => return,
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        return;
+                    }
+                };
+                let range = if self.state.crc_calc_range.trim().is_empty() {
+                    Ok((0, frame.len() - 1))
+                } else {
+                    parse_range_field(&self.state.crc_calc_range)
+                };
+                let Some((start, end)) = range.ok().and_then(|(s, e)| resolve_range(s, e, frame.len())) else {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("range must be within the frame (len {})", frame.len()));
+                    return;
+                };
+                ui.weak(format!("{} bytes checksummed", end - start + 1));
+                egui::Grid::new("crc_calc_grid").striped(true).num_columns(3).show(ui, |ui| {
+                    for algo in ChecksumAlgo::ALL {
+                        ui.label(algo.to_string());
+                        ui.monospace(algo.compute_hex(&frame[start..=end]));
+                        match algo.trailer_order(&frame, start, end) {
+                            Some(Endian::Big) => { ui.colored_label(egui::Color32::from_rgb(0x4c, 0xaf, 0x50), "✔ matches trailer (BE)"); }
+                            Some(Endian::Little) => { ui.colored_label(egui::Color32::from_rgb(0x4c, 0xaf, 0x50), "✔ matches trailer (LE)"); }
+                            None => { ui.label(""); }
+                        }
+                        ui.end_row();
+                    }
                 });
             });
 
             ui.separator();
 
+            ui.horizontal(|ui| {
+                ui.label("Config");
+                if ui.button("Save as default").on_hover_text("Load these rules and framing settings on startup").clicked() {
+                    match default_config_path() {
+                        Some(path) => match save_config(&self.state, &path) {
+                            Ok(()) => info!("saved config to {}", path.display()),
+                            Err(e) => error!("save config failed: {}", e),
+                        },
+                        None => error!("save config failed: no data directory"),
+                    }
+                }
+                if ui.button("Save…").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().set_file_name(DEFAULT_CONFIG_FILE).save_file() {
+                        if let Err(e) = save_config(&self.state, &path) {
+                            error!("save config failed: {}", e);
+                        }
+                    }
+                }
+                if ui.button("Load…").on_hover_text("Replace the watch items, labels, suspect rules and framing settings").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        if let Err(e) = load_config(&path).and_then(|config| config.apply(&mut self.state)) {
+                            error!("load config failed: {}", e);
+                        }
+                    }
+                }
+            });
+            ui.separator();
+
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.state.left_panel_tab, LeftPanelTab::Watch, "Watch list");
                 ui.selectable_value(&mut self.state.left_panel_tab, LeftPanelTab::Labels, "Message labels");
@@ -141,8 +1080,9 @@ impl eframe::App for ByteBusterApp {
             if self.state.left_panel_tab == LeftPanelTab::Watch {
                 ui.collapsing("Watch list", |ui| {
                 let mut to_start_edit: Option<usize> = None;
-                let mut to_save: Option<(usize, String, usize, usize)> = None;
+                let mut to_save: Option<WatchEdit> = None;
                 let mut to_delete: Option<usize> = None;
+                let mut to_move: Option<(usize, bool)> = None;
                 let mut cancel_edit: bool = false;
 
                 // Add form (stacked vertically, full width)
@@ -156,41 +1096,53 @@ impl eframe::App for ByteBusterApp {
                             ui.label("Name");
                             ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_watch_name));
                             ui.label("Index or range");
-                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_watch_range).hint_text("e.g. 4 or 4-5"));
+                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_watch_range).hint_text("e.g. 4, 4-5, 10.. or 10:+4"));
+                            field_error(ui, &self.state.new_watch_error);
                             ui.label("View");
-                            egui::ComboBox::from_id_source("add_watch_view")
-                                .width(w)
-                                .selected_text(self.state.new_watch_view.to_string())
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(&mut self.state.new_watch_view, WatchView::Hex, "Hex");
-                                    ui.selectable_value(&mut self.state.new_watch_view, WatchView::Text, "Text");
-                                    ui.selectable_value(&mut self.state.new_watch_view, WatchView::Binary, "Binary");
-                                });
+                            watch_view_picker(ui, "add_watch_view", w, &mut self.state.new_watch_view);
+                            if self.state.new_watch_view == WatchView::Enum {
+                                ui.label("Value names");
+                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_watch_enum).hint_text("e.g. 1=PING, 2=REQUEST"));
+                            }
+                            ui.label("Byte order");
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut self.state.new_watch_endian, Endian::Little, "Little-endian");
+                                ui.radio_value(&mut self.state.new_watch_endian, Endian::Big, "Big-endian");
+                            });
                             ui.label("Target");
                             egui::ComboBox::from_id_source("add_watch_target")
                                 .width(w)
                                 .selected_text(self.state.new_watch_target.to_string())
                                 .show_ui(ui, |ui| {
                                     ui.selectable_value(&mut self.state.new_watch_target, WatchTarget::All, "All messages");
-                                    for rule in &self.state.label_rules {
+                                    every_nth_option(ui, &mut self.state.new_watch_target);
+                                    for rule in &self.state.engine.label_rules {
                                         ui.selectable_value(&mut self.state.new_watch_target, WatchTarget::Label(rule.name.clone()), rule.name.clone());
                                     }
                                 });
+                            every_nth_fields(ui, &mut self.state.new_watch_target);
                             ui.add_space(8.0);
                             if ui.add_sized([w, 0.0], egui::Button::new("Add watch")).clicked() {
-                                if let Some((start, end)) = parse_index_range(&self.state.new_watch_range) {
-                                    let (start_index, end_index) = if start <= end { (start, end) } else { (end, start) };
-                                    self.state.watch_items.push(WatchItem {
+                                match parse_range_field(&self.state.new_watch_range).and_then(|range| Ok((range, parse_enum_names(&self.state.new_watch_enum)?))) {
+                                    Ok(((start_index, end_index), enum_names)) => {
+                                    self.state.engine.watch_items.push(WatchItem {
                                         name: self.state.new_watch_name.clone(),
                                         start_index,
                                         end_index,
                                         view: self.state.new_watch_view,
+                                        endian: self.state.new_watch_endian,
                                         target: self.state.new_watch_target.clone(),
+                                        enum_names,
                                     });
                                     self.state.new_watch_name.clear();
                                     self.state.new_watch_range.clear();
+                                    self.state.new_watch_enum.clear();
                                     self.state.new_watch_view = WatchView::Hex;
+                                    self.state.new_watch_endian = self.state.default_endian;
                                     self.state.new_watch_target = WatchTarget::All;
+                                    self.state.new_watch_error = None;
+                                    }
+                                    Err(e) => self.state.new_watch_error = Some(e),
                                 }
                             }
                         });
@@ -201,7 +1153,7 @@ impl eframe::App for ByteBusterApp {
                 ui.label("Current watch items");
                 ui.add_space(4.0);
 
-                for (i, item) in self.state.watch_items.iter().enumerate() {
+                for (i, item) in self.state.engine.watch_items.iter().enumerate() {
                     egui::Frame::group(ui.style())
                         .inner_margin(egui::Margin::symmetric(10.0, 8.0))
                         .outer_margin(egui::Margin::symmetric(0.0, 4.0))
@@ -215,34 +1167,39 @@ impl eframe::App for ByteBusterApp {
                                     ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_watch_name).hint_text("name"));
                                     ui.label("Index or range");
                                     ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_watch_range).hint_text("index or range"));
+                                    field_error(ui, &self.state.edit_watch_error);
                                     ui.label("View");
-                                    egui::ComboBox::from_id_source(format!("edit_watch_view_{}", i))
-                                        .width(w)
-                                        .selected_text(self.state.edit_watch_view.to_string())
-                                        .show_ui(ui, |ui| {
-                                            ui.selectable_value(&mut self.state.edit_watch_view, WatchView::Hex, "Hex");
-                                            ui.selectable_value(&mut self.state.edit_watch_view, WatchView::Text, "Text");
-                                            ui.selectable_value(&mut self.state.edit_watch_view, WatchView::Binary, "Binary");
-                                        });
+                                    watch_view_picker(ui, format!("edit_watch_view_{}", i), w, &mut self.state.edit_watch_view);
+                                    if self.state.edit_watch_view == WatchView::Enum {
+                                        ui.label("Value names");
+                                        ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_watch_enum).hint_text("e.g. 1=PING, 2=REQUEST"));
+                                    }
+                                    ui.label("Byte order");
+                                    ui.horizontal(|ui| {
+                                        ui.radio_value(&mut self.state.edit_watch_endian, Endian::Little, "Little-endian");
+                                        ui.radio_value(&mut self.state.edit_watch_endian, Endian::Big, "Big-endian");
+                                    });
                                     ui.label("Target");
                                     egui::ComboBox::from_id_source(format!("edit_watch_target_{}", i))
                                         .width(w)
                                         .selected_text(self.state.edit_watch_target.to_string())
                                         .show_ui(ui, |ui| {
                                             ui.selectable_value(&mut self.state.edit_watch_target, WatchTarget::All, "All messages");
-                                            for rule in &self.state.label_rules {
+                                            every_nth_option(ui, &mut self.state.edit_watch_target);
+                                            for rule in &self.state.engine.label_rules {
                                                 ui.selectable_value(&mut self.state.edit_watch_target, WatchTarget::Label(rule.name.clone()), rule.name.clone());
                                             }
                                         });
+                                    every_nth_fields(ui, &mut self.state.edit_watch_target);
                                     ui.add_space(10.0);
                                     let btn_w = ui.available_width();
                                     let save_clicked = ui
                                         .add_sized([btn_w, 0.0], egui::Button::new("Save"))
                                         .clicked();
                                     if save_clicked {
-                                        if let Some((s, e)) = parse_index_range(&self.state.edit_watch_range) {
-                                            let (start, end) = if s <= e { (s, e) } else { (e, s) };
-                                            to_save = Some((i, self.state.edit_watch_name.clone(), start, end));
+                                        match parse_range_field(&self.state.edit_watch_range).and_then(|range| Ok((range, parse_enum_names(&self.state.edit_watch_enum)?))) {
+                                            Ok(((start, end), enum_names)) => to_save = Some(WatchEdit { index: i, name: self.state.edit_watch_name.clone(), start, end, enum_names }),
+                                            Err(e) => self.state.edit_watch_error = Some(e),
                                         }
                                     }
                                     ui.add_space(4.0);
@@ -254,66 +1211,127 @@ impl eframe::App for ByteBusterApp {
                                 ui.vertical(|ui| {
                                     ui.strong(&item.name);
                                     ui.add_space(4.0);
-                                    ui.monospace(format!("[{}..{}]", item.start_index, item.end_index));
+                                    ui.monospace(format!("[{}]", format_index_range(item.start_index, item.end_index)));
                                     ui.add_space(2.0);
-                                    ui.label(format!("{} | {}", item.view, item.target));
+                                    ui.label(format!("{} | {} | {}", item.view, item.endian, item.target));
                                     ui.add_space(8.0);
                                     ui.horizontal(|ui| {
                                         if ui.button("Edit").clicked() { to_start_edit = Some(i); }
                                         if ui.button("Delete").clicked() { to_delete = Some(i); }
+                                        if ui.add_enabled(i > 0, egui::Button::new("⬆")).on_hover_text("Move up").clicked() { to_move = Some((i, true)); }
+                                        if ui.add_enabled(i + 1 < self.state.engine.watch_items.len(), egui::Button::new("⬇")).on_hover_text("Move down").clicked() { to_move = Some((i, false)); }
                                     });
                                 });
                             }
                         });
                 }
 
+                ui.add_space(6.0);
+                ui.collapsing("Statistics", |ui| {
+                    if self.state.watch_stats.is_empty() {
+                        ui.weak("No numeric samples yet (integer, enum and single signed-byte watches are recorded)");
+                    } else {
+                        egui::Grid::new("watch_stats_grid").striped(true).num_columns(6).show(ui, |ui| {
+                            ui.strong("Name");
+                            ui.strong("Count");
+                            ui.strong("Min");
+                            ui.strong("Max");
+                            ui.strong("Mean");
+                            ui.strong("Recent");
+                            ui.end_row();
+                            for (wi, item) in self.state.engine.watch_items.iter().enumerate() {
+                                if let Some(stats) = self.state.watch_stats.get(&item.name) {
+                                    ui.label(&item.name);
+                                    ui.monospace(stats.count.to_string());
+                                    ui.monospace(stats.min.to_string());
+                                    ui.monospace(stats.max.to_string());
+                                    ui.monospace(format!("{:.2}", stats.mean()));
+                                    match self.state.watch_history.get(&item.name) {
+                                        Some(history) => sparkline(ui, history, watch_highlight_color(wi)),
+                                        None => { ui.label("-"); }
+                                    }
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("History");
+                        if ui.add(egui::DragValue::new(&mut self.state.watch_history_len).clamp_range(2..=10_000).suffix(" values")).changed() {
+                            let len = self.state.watch_history_len;
+                            for history in self.state.watch_history.values_mut() {
+                                while history.len() > len { history.pop_front(); }
+                            }
+                        }
+                        if ui.button("Reset statistics").clicked() {
+                            self.state.watch_stats.clear();
+                            self.state.watch_history.clear();
+                        }
+                    });
+                });
+
+                if let Some((i, up)) = to_move {
+                    move_item(&mut self.state.engine.watch_items, i, up, &mut self.state.edit_watch_idx);
+                }
                 if let Some(i) = to_start_edit {
                     self.state.edit_watch_idx = Some(i);
-                    if let Some(item) = self.state.watch_items.get(i) {
+                    if let Some(item) = self.state.engine.watch_items.get(i) {
                         self.state.edit_watch_name = item.name.clone();
-                        self.state.edit_watch_range = format!("{}-{}", item.start_index, item.end_index);
+                        self.state.edit_watch_range = format_index_range(item.start_index, item.end_index);
                         self.state.edit_watch_view = item.view;
+                        self.state.edit_watch_endian = item.endian;
                         self.state.edit_watch_target = item.target.clone();
+                        self.state.edit_watch_enum = format_enum_names(&item.enum_names);
                     }
+                    self.state.edit_watch_error = None;
                 }
-                if let Some((i, name, start, end)) = to_save {
-                    if let Some(item) = self.state.watch_items.get_mut(i) {
-                        item.name = name;
-                        item.start_index = start;
-                        item.end_index = end;
+                if let Some(edit) = to_save {
+                    if let Some(item) = self.state.engine.watch_items.get_mut(edit.index) {
+                        item.name = edit.name;
+                        item.enum_names = edit.enum_names;
+                        item.start_index = edit.start;
+                        item.end_index = edit.end;
                         item.view = self.state.edit_watch_view;
+                        item.endian = self.state.edit_watch_endian;
                         item.target = self.state.edit_watch_target.clone();
                     }
                     self.state.edit_watch_idx = None;
                     self.state.edit_watch_name.clear();
                     self.state.edit_watch_range.clear();
                     self.state.edit_watch_view = WatchView::Hex;
+                    self.state.edit_watch_endian = Endian::Little;
                     self.state.edit_watch_target = WatchTarget::All;
+                    self.state.edit_watch_error = None;
                 }
                 if cancel_edit {
                     self.state.edit_watch_idx = None;
                     self.state.edit_watch_name.clear();
                     self.state.edit_watch_range.clear();
                     self.state.edit_watch_view = WatchView::Hex;
+                    self.state.edit_watch_endian = Endian::Little;
                     self.state.edit_watch_target = WatchTarget::All;
+                    self.state.edit_watch_error = None;
                 }
                 if let Some(i) = to_delete {
-                    if i < self.state.watch_items.len() {
-                        self.state.watch_items.remove(i);
+                    if i < self.state.engine.watch_items.len() {
+                        self.state.engine.watch_items.remove(i);
                     }
                     // Reset edit state if needed
                     self.state.edit_watch_idx = None;
                     self.state.edit_watch_name.clear();
                     self.state.edit_watch_range.clear();
                     self.state.edit_watch_view = WatchView::Hex;
+                    self.state.edit_watch_endian = Endian::Little;
                     self.state.edit_watch_target = WatchTarget::All;
+                    self.state.edit_watch_error = None;
                 }
                 });
             } else if self.state.left_panel_tab == LeftPanelTab::Labels {
                 ui.collapsing("Message labels", |ui| {
                     let mut to_start_edit: Option<usize> = None;
-                    let mut to_save: Option<(usize, String, usize, usize, Vec<u8>)> = None;
+                    let mut to_save: Option<(usize, LabelRule)> = None;
                     let mut to_delete: Option<usize> = None;
+                    let mut to_move: Option<(usize, bool)> = None;
                     let mut cancel_edit: bool = false;
 
                     // Add form first (full width)
@@ -326,20 +1344,26 @@ impl eframe::App for ByteBusterApp {
                                 ui.add_space(6.0);
                                 ui.label("Name");
                                 ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_label_name).hint_text("name"));
-                                ui.label("Index or range");
-                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_label_range).hint_text("e.g. 3 or 3-4"));
-                                ui.label("Value hex");
-                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_label_value_hex).hint_text("e.g. 01 or AA BB"));
+                                ui.horizontal(|ui| {
+                                    ui.label("Color");
+                                    ui.color_edit_button_srgb(&mut self.state.new_label_color);
+                                });
+                                ui.push_id("add_label_conditions", |ui| {
+                                    label_condition_rows(ui, &mut self.state.new_label_conditions);
+                                });
+                                field_error(ui, &self.state.new_label_error);
                                 ui.add_space(8.0);
                                 if ui.add_sized([w, 0.0], egui::Button::new("Add label")).clicked() {
-                                    if let Some((start, end)) = parse_index_range(&self.state.new_label_range) {
-                                        if let Ok(value) = parse_hex_bytes(&self.state.new_label_value_hex) {
-                                            let (start_index, end_index) = if start <= end { (start, end) } else { (end, start) };
-                                            self.state.label_rules.push(LabelRule { name: self.state.new_label_name.clone(), start_index, end_index, value });
+                                    match build_label_rule(&self.state.new_label_name, &self.state.new_label_conditions, self.state.new_label_color) {
+                                        Ok(rule) => {
+                                            self.state.engine.label_rules.push(rule);
+                                            self.state.refresh_annotations();
                                             self.state.new_label_name.clear();
-                                            self.state.new_label_range.clear();
-                                            self.state.new_label_value_hex.clear();
+                                            self.state.new_label_conditions = vec![LabelConditionForm::default()];
+                                            self.state.new_label_color = DEFAULT_LABEL_COLOR;
+                                            self.state.new_label_error = None;
                                         }
+                                        Err(e) => self.state.new_label_error = Some(e),
                                     }
                                 }
                             });
@@ -350,7 +1374,7 @@ impl eframe::App for ByteBusterApp {
                     ui.label("Current label rules");
                     ui.add_space(4.0);
 
-                    for (i, rule) in self.state.label_rules.iter().enumerate() {
+                    for (i, rule) in self.state.engine.label_rules.iter().enumerate() {
                         egui::Frame::group(ui.style())
                             .inner_margin(egui::Margin::symmetric(10.0, 8.0))
                             .outer_margin(egui::Margin::symmetric(0.0, 4.0))
@@ -361,20 +1385,22 @@ impl eframe::App for ByteBusterApp {
                                     ui.vertical(|ui| {
                                         ui.label("Name");
                                         ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_label_name).hint_text("name"));
-                                        ui.label("Index or range");
-                                        ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_label_range).hint_text("index or range"));
-                                        ui.label("Value hex");
-                                        ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_label_value_hex).hint_text("e.g. 01 or AA BB"));
+                                        ui.horizontal(|ui| {
+                                            ui.label("Color");
+                                            ui.color_edit_button_srgb(&mut self.state.edit_label_color);
+                                        });
+                                        ui.push_id(format!("edit_label_conditions_{}", i), |ui| {
+                                            label_condition_rows(ui, &mut self.state.edit_label_conditions);
+                                        });
+                                        field_error(ui, &self.state.edit_label_error);
                                         ui.add_space(10.0);
                                         let save_clicked = ui
                                             .add_sized([w, 0.0], egui::Button::new("Save"))
                                             .clicked();
                                         if save_clicked {
-                                            if let Some((s, e)) = parse_index_range(&self.state.edit_label_range) {
-                                                if let Ok(value) = parse_hex_bytes(&self.state.edit_label_value_hex) {
-                                                    let (start, end) = if s <= e { (s, e) } else { (e, s) };
-                                                    to_save = Some((i, self.state.edit_label_name.clone(), start, end, value));
-                                                }
+                                            match build_label_rule(&self.state.edit_label_name, &self.state.edit_label_conditions, self.state.edit_label_color) {
+                                                Ok(rule) => to_save = Some((i, rule)),
+                                                Err(e) => self.state.edit_label_error = Some(e),
                                             }
                                         }
                                         ui.add_space(4.0);
@@ -384,61 +1410,74 @@ impl eframe::App for ByteBusterApp {
                                     });
                                 } else {
                                     ui.vertical(|ui| {
-                                        ui.strong(&rule.name);
+                                        let [r, g, b] = rule.color;
+                                        ui.colored_label(egui::Color32::from_rgb(r, g, b), egui::RichText::new(&rule.name).strong());
                                         ui.add_space(4.0);
-                                        ui.monospace(format!("[{}..{}] == {}", rule.start_index, rule.end_index, hex::encode_upper(&rule.value)));
+                                        for (ci, cond) in rule.conditions.iter().enumerate() {
+                                            let prefix = if ci == 0 { "" } else { "AND " };
+                                            ui.monospace(format!("{}{}", prefix, format_label_condition(cond, self.state.hex_uppercase)));
+                                        }
                                         ui.add_space(8.0);
                                         ui.horizontal(|ui| {
                                             if ui.button("Edit").clicked() { to_start_edit = Some(i); }
                                             if ui.button("Delete").clicked() { to_delete = Some(i); }
+                                            if ui.add_enabled(i > 0, egui::Button::new("⬆")).on_hover_text("Move up").clicked() { to_move = Some((i, true)); }
+                                            if ui.add_enabled(i + 1 < self.state.engine.label_rules.len(), egui::Button::new("⬇")).on_hover_text("Move down").clicked() { to_move = Some((i, false)); }
                                         });
                                     });
                                 }
                             });
                     }
 
+                    if let Some((i, up)) = to_move {
+                        move_item(&mut self.state.engine.label_rules, i, up, &mut self.state.edit_label_idx);
+                        self.state.refresh_annotations();
+                    }
                     if let Some(i) = to_start_edit {
                         self.state.edit_label_idx = Some(i);
-                        if let Some(rule) = self.state.label_rules.get(i) {
+                        if let Some(rule) = self.state.engine.label_rules.get(i) {
                             self.state.edit_label_name = rule.name.clone();
-                            self.state.edit_label_range = format!("{}-{}", rule.start_index, rule.end_index);
-                            self.state.edit_label_value_hex = hex::encode_upper(&rule.value);
+                            self.state.edit_label_conditions = rule.conditions.iter().map(LabelConditionForm::from).collect();
+                            self.state.edit_label_color = rule.color;
                         }
+                        self.state.edit_label_error = None;
                     }
-                    if let Some((i, name, start, end, value)) = to_save {
-                        if let Some(rule) = self.state.label_rules.get_mut(i) {
-                            rule.name = name;
-                            rule.start_index = start;
-                            rule.end_index = end;
-                            rule.value = value;
+                    if let Some((i, new_rule)) = to_save {
+                        if let Some(rule) = self.state.engine.label_rules.get_mut(i) {
+                            *rule = new_rule;
                         }
+                        self.state.refresh_annotations();
                         self.state.edit_label_idx = None;
                         self.state.edit_label_name.clear();
-                        self.state.edit_label_range.clear();
-                        self.state.edit_label_value_hex.clear();
+                        self.state.edit_label_conditions.clear();
+                        self.state.edit_label_error = None;
                     }
                     if cancel_edit {
                         self.state.edit_label_idx = None;
                         self.state.edit_label_name.clear();
-                        self.state.edit_label_range.clear();
-                        self.state.edit_label_value_hex.clear();
+                        self.state.edit_label_conditions.clear();
+                        self.state.edit_label_error = None;
                     }
                     if let Some(i) = to_delete {
-                        if i < self.state.label_rules.len() {
-                            self.state.label_rules.remove(i);
+                        if i < self.state.engine.label_rules.len() {
+                            self.state.engine.label_rules.remove(i);
                         }
+                        self.state.refresh_annotations();
                         self.state.edit_label_idx = None;
                         self.state.edit_label_name.clear();
-                        self.state.edit_label_range.clear();
-                        self.state.edit_label_value_hex.clear();
+                        self.state.edit_label_conditions.clear();
+                        self.state.edit_label_error = None;
                     }
                 });
             } else {
                 ui.collapsing("Expected data", |ui| {
                     let mut to_start_edit: Option<usize> = None;
-                    let mut to_save: Option<(usize, String, usize, usize, ExpectedKind, String, WatchTarget, app::suspects::Severity)> = None;
+                    let mut to_save: Option<SuspectEdit> = None;
                     let mut to_delete: Option<usize> = None;
+                    let mut to_move: Option<(usize, bool)> = None;
                     let mut cancel_edit: bool = false;
+                    let mut to_toggle: Option<usize> = None;
+                    let mut to_reset: Option<usize> = None;
 
                     // Add form
                     egui::Frame::group(ui.style())
@@ -451,13 +1490,16 @@ impl eframe::App for ByteBusterApp {
                                 ui.label("Name");
                                 ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_suspect_name));
                                 ui.label("Index or range");
-                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_suspect_range).hint_text("e.g. 10-13"));
+                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_suspect_range).hint_text("e.g. 10-13 or 10:+4"));
+                                field_error(ui, &self.state.new_suspect_range_error);
                                 ui.label("Expected kind");
                                 egui::ComboBox::from_id_source("suspect_kind_add").width(w)
-                                    .selected_text(match self.state.new_suspect_kind { app::suspects::ExpectedKind::Text => "Text", app::suspects::ExpectedKind::Hex => "Hex" })
+                                    .selected_text(self.state.new_suspect_kind.to_string())
                                     .show_ui(ui, |ui| {
                                         ui.selectable_value(&mut self.state.new_suspect_kind, app::suspects::ExpectedKind::Text, "Text");
                                         ui.selectable_value(&mut self.state.new_suspect_kind, app::suspects::ExpectedKind::Hex, "Hex");
+                                        ui.selectable_value(&mut self.state.new_suspect_kind, app::suspects::ExpectedKind::OneOf, "One of");
+                                        checksum_kind_option(ui, &mut self.state.new_suspect_kind);
                                     });
                                 ui.label("Severity");
                                 egui::ComboBox::from_id_source("suspect_severity_add").width(w)
@@ -467,37 +1509,69 @@ impl eframe::App for ByteBusterApp {
                                         ui.selectable_value(&mut self.state.new_suspect_severity, app::suspects::Severity::Warning, "Warning");
                                         ui.selectable_value(&mut self.state.new_suspect_severity, app::suspects::Severity::Critical, "Critical");
                                     });
-                                ui.label("Expected value");
-                                let hint = match self.state.new_suspect_kind { app::suspects::ExpectedKind::Text => "e.g. PING", app::suspects::ExpectedKind::Hex => "e.g. 50 49 4E 47" };
-                                ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_suspect_value).hint_text(hint));
+                                ui.horizontal(|ui| {
+                                    ui.label("Critical after");
+                                    ui.add(egui::DragValue::new(&mut self.state.new_suspect_escalate).suffix(" in a row"))
+                                        .on_hover_text("Escalate to Critical after this many consecutive violations; 0 never escalates");
+                                });
+                                let hint = match self.state.new_suspect_kind {
+                                    app::suspects::ExpectedKind::Text => "e.g. PING",
+                                    app::suspects::ExpectedKind::Hex => "e.g. 50 49 4E 47",
+                                    app::suspects::ExpectedKind::OneOf => "e.g. 00, 01, \"OK\"",
+                                    app::suspects::ExpectedKind::Checksum { .. } => "",
+                                };
+                                if !checksum_kind_fields(ui, "suspect_algo_add", w, &mut self.state.new_suspect_kind, &mut self.state.new_suspect_stored) {
+                                    ui.label("Expected value");
+                                    ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.new_suspect_value).hint_text(hint));
+                                }
+                                field_error(ui, &self.state.new_suspect_value_error);
+                                if self.state.new_suspect_kind == ExpectedKind::Text {
+                                    ui.checkbox(&mut self.state.new_suspect_trim, "Trim whitespace")
+                                        .on_hover_text("Ignore space and NUL padding around the text");
+                                }
                                 ui.label("Target");
                                 egui::ComboBox::from_id_source("suspect_target_add").width(w)
                                     .selected_text(self.state.new_suspect_target.to_string())
                                     .show_ui(ui, |ui| {
                                         ui.selectable_value(&mut self.state.new_suspect_target, WatchTarget::All, "All messages");
-                                        for rule in &self.state.label_rules {
+                                        every_nth_option(ui, &mut self.state.new_suspect_target);
+                                        for rule in &self.state.engine.label_rules {
                                             ui.selectable_value(&mut self.state.new_suspect_target, WatchTarget::Label(rule.name.clone()), rule.name.clone());
                                         }
                                     });
+                                every_nth_fields(ui, &mut self.state.new_suspect_target);
                                 ui.add_space(8.0);
                                 if ui.add_sized([w, 0.0], egui::Button::new("Add expectation")).clicked() {
-                                    if let Some((s, e)) = parse_index_range(&self.state.new_suspect_range) {
-                                        let (start, end) = if s <= e { (s, e) } else { (e, s) };
-                                        self.state.suspect_rules.push(SuspectRule {
+                                    let range = parse_range_field(&self.state.new_suspect_range);
+                                    let kind = form_expected_kind(self.state.new_suspect_kind, range.clone().unwrap_or_default(), &self.state.new_suspect_stored)
+                                        .and_then(|kind| validate_expected_value(kind, &self.state.new_suspect_value).map(|_| kind));
+                                    self.state.new_suspect_range_error = range.as_ref().err().cloned();
+                                    self.state.new_suspect_value_error = kind.as_ref().err().cloned();
+                                    if let (Ok((start, end)), Ok(kind)) = (range, kind) {
+                                        self.state.engine.suspect_rules.push(SuspectRule {
                                             name: self.state.new_suspect_name.clone(),
                                             start_index: start,
                                             end_index: end,
-                                            expected_kind: self.state.new_suspect_kind,
+                                            expected_kind: kind,
                                             expected_value: self.state.new_suspect_value.clone(),
                                             target: self.state.new_suspect_target.clone(),
                                             severity: self.state.new_suspect_severity,
+                                            trim: self.state.new_suspect_trim,
+                                            enabled: true,
+                                            violation_count: 0,
+                                            escalate_after: self.state.new_suspect_escalate,
+                                            consecutive_violations: 0,
                                         });
+                                        self.state.refresh_annotations();
                                         self.state.new_suspect_name.clear();
                                         self.state.new_suspect_range.clear();
                                         self.state.new_suspect_value.clear();
                                         self.state.new_suspect_kind = app::suspects::ExpectedKind::Text;
                                         self.state.new_suspect_target = WatchTarget::All;
                                         self.state.new_suspect_severity = app::suspects::Severity::Warning;
+                                        self.state.new_suspect_trim = false;
+                                        self.state.new_suspect_escalate = 0;
+                                        self.state.new_suspect_stored.clear();
                                     }
                                 }
                             });
@@ -505,10 +1579,13 @@ impl eframe::App for ByteBusterApp {
 
                     ui.add_space(6.0);
                     ui.separator();
+                    ui.checkbox(&mut self.state.os_notifications, "OS notifications")
+                        .on_hover_text("Show a desktop notification when a Critical expectation fails (at most one every 10s)");
+                    ui.separator();
                     ui.label("Current expectations");
                     ui.add_space(4.0);
 
-                    for (i, r) in self.state.suspect_rules.iter().enumerate() {
+                    for (i, r) in self.state.engine.suspect_rules.iter().enumerate() {
                         egui::Frame::group(ui.style())
                             .inner_margin(egui::Margin::symmetric(10.0, 8.0))
                             .outer_margin(egui::Margin::symmetric(0.0, 4.0))
@@ -521,26 +1598,38 @@ impl eframe::App for ByteBusterApp {
                                         ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_suspect_name));
                                         ui.label("Index or range");
                                         ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_suspect_range));
+                                        field_error(ui, &self.state.edit_suspect_range_error);
                                         ui.label("Expected kind");
                                         egui::ComboBox::from_id_source(format!("suspect_kind_edit_{}", i))
                                             .width(w)
-                                            .selected_text(match self.state.edit_suspect_kind { app::suspects::ExpectedKind::Text => "Text", app::suspects::ExpectedKind::Hex => "Hex" })
+                                            .selected_text(self.state.edit_suspect_kind.to_string())
                                             .show_ui(ui, |ui| {
                                                 ui.selectable_value(&mut self.state.edit_suspect_kind, app::suspects::ExpectedKind::Text, "Text");
                                                 ui.selectable_value(&mut self.state.edit_suspect_kind, app::suspects::ExpectedKind::Hex, "Hex");
+                                                ui.selectable_value(&mut self.state.edit_suspect_kind, app::suspects::ExpectedKind::OneOf, "One of");
+                                                checksum_kind_option(ui, &mut self.state.edit_suspect_kind);
                                             });
-                                        ui.label("Expected value");
-                                        ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_suspect_value));
+                                        if !checksum_kind_fields(ui, format!("suspect_algo_edit_{}", i), w, &mut self.state.edit_suspect_kind, &mut self.state.edit_suspect_stored) {
+                                            ui.label("Expected value");
+                                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut self.state.edit_suspect_value));
+                                        }
+                                        field_error(ui, &self.state.edit_suspect_value_error);
+                                        if self.state.edit_suspect_kind == ExpectedKind::Text {
+                                            ui.checkbox(&mut self.state.edit_suspect_trim, "Trim whitespace")
+                                                .on_hover_text("Ignore space and NUL padding around the text");
+                                        }
                                         ui.label("Target");
                                         egui::ComboBox::from_id_source(format!("suspect_target_edit_{}", i))
                                             .width(w)
                                             .selected_text(self.state.edit_suspect_target.to_string())
                                             .show_ui(ui, |ui| {
                                                 ui.selectable_value(&mut self.state.edit_suspect_target, WatchTarget::All, "All messages");
-                                                for rule in &self.state.label_rules {
+                                                every_nth_option(ui, &mut self.state.edit_suspect_target);
+                                                for rule in &self.state.engine.label_rules {
                                                     ui.selectable_value(&mut self.state.edit_suspect_target, WatchTarget::Label(rule.name.clone()), rule.name.clone());
                                                 }
                                             });
+                                        every_nth_fields(ui, &mut self.state.edit_suspect_target);
                                         ui.label("Severity");
                                         egui::ComboBox::from_id_source(format!("suspect_severity_edit_{}", i))
                                             .width(w)
@@ -550,21 +1639,32 @@ impl eframe::App for ByteBusterApp {
                                                 ui.selectable_value(&mut self.state.edit_suspect_severity, app::suspects::Severity::Warning, "Warning");
                                                 ui.selectable_value(&mut self.state.edit_suspect_severity, app::suspects::Severity::Critical, "Critical");
                                             });
+                                        ui.horizontal(|ui| {
+                                            ui.label("Critical after");
+                                            ui.add(egui::DragValue::new(&mut self.state.edit_suspect_escalate).suffix(" in a row"))
+                                                .on_hover_text("Escalate to Critical after this many consecutive violations; 0 never escalates");
+                                        });
                                         ui.add_space(10.0);
                                         let save_clicked = ui.add_sized([w, 0.0], egui::Button::new("Save")).clicked();
                                         if save_clicked {
-                                            if let Some((s, e)) = parse_index_range(&self.state.edit_suspect_range) {
-                                                let (start, end) = if s <= e { (s, e) } else { (e, s) };
-                                                to_save = Some((
-                                                    i,
-                                                    self.state.edit_suspect_name.clone(),
+                                            let range = parse_range_field(&self.state.edit_suspect_range);
+                                            let kind = form_expected_kind(self.state.edit_suspect_kind, range.clone().unwrap_or_default(), &self.state.edit_suspect_stored)
+                                                .and_then(|kind| validate_expected_value(kind, &self.state.edit_suspect_value).map(|_| kind));
+                                            self.state.edit_suspect_range_error = range.as_ref().err().cloned();
+                                            self.state.edit_suspect_value_error = kind.as_ref().err().cloned();
+                                            if let (Ok((start, end)), Ok(kind)) = (range, kind) {
+                                                to_save = Some(SuspectEdit {
+                                                    index: i,
+                                                    name: self.state.edit_suspect_name.clone(),
                                                     start,
                                                     end,
-                                                    self.state.edit_suspect_kind,
-                                                    self.state.edit_suspect_value.clone(),
-                                                    self.state.edit_suspect_target.clone(),
-                                                    self.state.edit_suspect_severity,
-                                                ));
+                                                    kind,
+                                                    value: self.state.edit_suspect_value.clone(),
+                                                    target: self.state.edit_suspect_target.clone(),
+                                                    severity: self.state.edit_suspect_severity,
+                                                    trim: self.state.edit_suspect_trim,
+                                                    escalate_after: self.state.edit_suspect_escalate,
+                                                });
                                             }
                                         }
                                         ui.add_space(4.0);
@@ -572,163 +1672,904 @@ impl eframe::App for ByteBusterApp {
                                     });
                                 } else {
                                     ui.vertical(|ui| {
-                                        ui.strong(&r.name);
+                                        ui.horizontal(|ui| {
+                                            let mut enabled = r.enabled;
+                                            if ui.checkbox(&mut enabled, "").on_hover_text("Enabled").changed() { to_toggle = Some(i); }
+                                            ui.add_enabled(r.enabled, egui::Label::new(egui::RichText::new(&r.name).strong()));
+                                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                                if ui.small_button("Reset").on_hover_text("Reset violation count").clicked() { to_reset = Some(i); }
+                                                if r.effective_severity() != r.severity {
+                                                    ui.colored_label(egui::Color32::RED, "escalated")
+                                                        .on_hover_text(format!("{} violations in a row", r.consecutive_violations));
+                                                }
+                                                let count = egui::RichText::new(format!("{}×", r.violation_count));
+                                                if r.violation_count > 0 {
+                                                    ui.label(count.color(ui.visuals().warn_fg_color)).on_hover_text("Violations");
+                                                } else {
+                                                    ui.weak(count).on_hover_text("Violations");
+                                                }
+                                            });
+                                        });
                                         ui.add_space(4.0);
-                                        let kind = match r.expected_kind { app::suspects::ExpectedKind::Text => "Text", app::suspects::ExpectedKind::Hex => "Hex" };
-                                        ui.monospace(format!("[{}..{}] {} -> {} ({})", r.start_index, r.end_index, kind, r.expected_value, match r.severity { app::suspects::Severity::Info => "Info", app::suspects::Severity::Warning => "Warning", app::suspects::Severity::Critical => "Critical" }));
+                                        let kind = if r.trim && r.expected_kind == ExpectedKind::Text { "Text (trimmed)".to_string() } else { r.expected_kind.to_string() };
+                                        let value = match r.expected_kind {
+                                            ExpectedKind::Checksum { stored_at, .. } => format!("stored at [{}]", format_index_range(stored_at.0, stored_at.1)),
+                                            _ => r.expected_value.clone(),
+                                        };
+                                        ui.monospace(format!("[{}] {} -> {} ({})", format_index_range(r.start_index, r.end_index), kind, value, match r.severity { app::suspects::Severity::Info => "Info", app::suspects::Severity::Warning => "Warning", app::suspects::Severity::Critical => "Critical" }));
+                                        if r.escalate_after > 0 {
+                                            ui.weak(format!("Critical after {} in a row", r.escalate_after));
+                                        }
                                         ui.add_space(8.0);
                                         ui.horizontal(|ui| {
                                             if ui.button("Edit").clicked() { to_start_edit = Some(i); }
                                             if ui.button("Delete").clicked() { to_delete = Some(i); }
+                                            if ui.add_enabled(i > 0, egui::Button::new("⬆")).on_hover_text("Move up").clicked() { to_move = Some((i, true)); }
+                                            if ui.add_enabled(i + 1 < self.state.engine.suspect_rules.len(), egui::Button::new("⬇")).on_hover_text("Move down").clicked() { to_move = Some((i, false)); }
                                         });
                                     });
                                 }
                             });
                     }
 
+                    if let Some((i, up)) = to_move {
+                        move_item(&mut self.state.engine.suspect_rules, i, up, &mut self.state.edit_suspect_idx);
+                        // Parked connections keep their counters with the rule
+                        let count = self.state.engine.suspect_rules.len();
+                        for conn in &mut self.state.connections {
+                            conn.suspect_runs.resize(count, (0, 0));
+                            move_item(&mut conn.suspect_runs, i, up, &mut None);
+                        }
+                        self.state.refresh_annotations();
+                    }
+                    if let Some(i) = to_reset {
+                        if let Some(r) = self.state.engine.suspect_rules.get_mut(i) {
+                            r.violation_count = 0;
+                            r.consecutive_violations = 0;
+                        }
+                    }
+                    if let Some(i) = to_toggle {
+                        if let Some(r) = self.state.engine.suspect_rules.get_mut(i) {
+                            r.enabled = !r.enabled;
+                        }
+                        self.state.refresh_annotations();
+                    }
                     if let Some(i) = to_start_edit {
                         self.state.edit_suspect_idx = Some(i);
-                        if let Some(r) = self.state.suspect_rules.get(i) {
+                        if let Some(r) = self.state.engine.suspect_rules.get(i) {
                             self.state.edit_suspect_name = r.name.clone();
-                            self.state.edit_suspect_range = format!("{}-{}", r.start_index, r.end_index);
+                            self.state.edit_suspect_range = format_index_range(r.start_index, r.end_index);
                             self.state.edit_suspect_kind = r.expected_kind;
                             self.state.edit_suspect_value = r.expected_value.clone();
                             self.state.edit_suspect_target = r.target.clone();
                             self.state.edit_suspect_severity = r.severity;
+                            self.state.edit_suspect_trim = r.trim;
+                            self.state.edit_suspect_escalate = r.escalate_after;
+                            self.state.edit_suspect_stored = match r.expected_kind {
+                                ExpectedKind::Checksum { stored_at, .. } => format_index_range(stored_at.0, stored_at.1),
+                                _ => String::new(),
+                            };
                         }
+                        self.state.edit_suspect_range_error = None;
+                        self.state.edit_suspect_value_error = None;
                     }
-                    if let Some((i, name, start, end, kind, value, target, severity)) = to_save {
-                        if let Some(r) = self.state.suspect_rules.get_mut(i) {
-                            r.name = name;
-                            r.start_index = start;
-                            r.end_index = end;
-                            r.expected_kind = kind;
-                            r.expected_value = value;
-                            r.target = target;
-                            r.severity = severity;
+                    if let Some(edit) = to_save {
+                        if let Some(r) = self.state.engine.suspect_rules.get_mut(edit.index) {
+                            r.name = edit.name;
+                            r.start_index = edit.start;
+                            r.end_index = edit.end;
+                            r.expected_kind = edit.kind;
+                            r.expected_value = edit.value;
+                            r.target = edit.target;
+                            r.severity = edit.severity;
+                            r.trim = edit.trim;
+                            r.escalate_after = edit.escalate_after;
                         }
+                        self.state.refresh_annotations();
                         self.state.edit_suspect_idx = None;
                         self.state.edit_suspect_name.clear();
                         self.state.edit_suspect_range.clear();
                         self.state.edit_suspect_value.clear();
+                        self.state.edit_suspect_range_error = None;
+                        self.state.edit_suspect_value_error = None;
                     }
                     if cancel_edit {
                         self.state.edit_suspect_idx = None;
                         self.state.edit_suspect_name.clear();
                         self.state.edit_suspect_range.clear();
                         self.state.edit_suspect_value.clear();
+                        self.state.edit_suspect_range_error = None;
+                        self.state.edit_suspect_value_error = None;
                     }
                     if let Some(i) = to_delete {
-                        if i < self.state.suspect_rules.len() {
-                            self.state.suspect_rules.remove(i);
+                        if i < self.state.engine.suspect_rules.len() {
+                            self.state.engine.suspect_rules.remove(i);
+                            for conn in &mut self.state.connections {
+                                if i < conn.suspect_runs.len() {
+                                    conn.suspect_runs.remove(i);
+                                }
+                            }
                         }
+                        self.state.refresh_annotations();
                         self.state.edit_suspect_idx = None;
                         self.state.edit_suspect_name.clear();
                         self.state.edit_suspect_range.clear();
                         self.state.edit_suspect_value.clear();
+                        self.state.edit_suspect_range_error = None;
+                        self.state.edit_suspect_value_error = None;
                     }
                 });
             }
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
+            // `Some(true)` jumps to the newest message, `Some(false)` to the oldest
+            let mut jump_to_latest: Option<bool> = None;
             ui.horizontal(|ui| {
                 ui.heading("Incoming messages");
-                if ui.button("Clear").clicked() {
-                    self.state.received_messages.clear();
-                    self.incoming_buffer.clear();
-                    self.state.critical_active = false;
+                if ui.button("Clear").on_hover_text(ctx.format_shortcut(&CLEAR_SHORTCUT)).clicked() {
+                    self.clear_messages();
+                }
+                if ui.button("Reset stats").on_hover_text("Zero counters and statistics; keeps messages and rules").clicked() {
+                    self.state.reset_stats();
+                }
+                if ui.button("Re-evaluate").on_hover_text("Recompute violation counts and watch statistics over the stored messages with the current rules").clicked() {
+                    self.state.reevaluate_all();
+                }
+                let (pause_text, pause_hint) = if self.state.paused {
+                    ("▶ Resume", "Store the held frames and continue (Space)")
+                } else {
+                    ("⏸ Pause", "Hold new frames back while you inspect the list; the connection stays open (Space)")
+                };
+                if ui.button(pause_text).on_hover_text(pause_hint).clicked() {
+                    let paused = !self.state.paused;
+                    self.state.set_paused(paused);
+                }
+                if self.state.paused {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("Paused ({} held)", self.state.held_frames.len()));
+                }
+                if self.state.dropped_messages > 0 {
+                    let dropped = ui.colored_label(ui.visuals().warn_fg_color, format!("dropped {} older messages", self.state.dropped_messages))
+                        .on_hover_text("Discarded by the message cap; raise it under Settings or export. Click to reset");
+                    if dropped.interact(egui::Sense::click()).clicked() {
+                        self.state.dropped_messages = 0;
+                    }
+                }
+                if self.state.skipped_bytes > 0 {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("{} bytes skipped", self.state.skipped_bytes))
+                        .on_hover_text("Bytes discarded before a start delimiter; many usually means noise on the line or the wrong start pattern");
                 }
                 ui.add_space(8.0);
-                ui.checkbox(&mut self.state.display_as_text, "Display as text");
-            });
-            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
-                let mut any_critical = false;
-                for (i, msg) in self.state.received_messages.iter().enumerate() {
-                    ui.add_space(4.0);
-                    egui::Frame::group(ui.style())
-                        .outer_margin(egui::Margin::symmetric(0.0, 4.0))
-                        .inner_margin(egui::Margin::symmetric(12.0, 10.0))
-                        .show(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                let maybe_label = find_message_label(msg, &self.state.label_rules);
-                                ui.strong(match maybe_label {
-                                    Some(name) => name,
-                                    None => format!("Message {}", i + 1),
-                                });
-                                ui.add_space(8.0);
-                                ui.label(format!("{} bytes", msg.len()));
-                            });
-                            ui.add_space(6.0);
-                            if self.state.display_as_text {
-                                let text = String::from_utf8_lossy(msg);
-                                ui.monospace(text);
-                            } else {
-                                ui.monospace(hex::encode_upper(msg));
+                ui.label("Display");
+                ui.selectable_value(&mut self.state.display_mode, DisplayMode::Hex, "Hex");
+                ui.selectable_value(&mut self.state.display_mode, DisplayMode::Text, "Text");
+                ui.selectable_value(&mut self.state.display_mode, DisplayMode::HexDump, "Hexdump");
+                ui.selectable_value(&mut self.state.display_mode, DisplayMode::ControlPictures, "Control chars")
+                    .on_hover_text("Text with CR, LF, tab and other control bytes shown as ␍ ␊ ␉");
+                ui.add_space(8.0);
+                ui.checkbox(&mut self.state.autoscroll, "Autoscroll")
+                    .on_hover_text("Follow new messages while scrolled to the bottom");
+                if ui.small_button("⤒ Top").clicked() { jump_to_latest = Some(false); }
+                if ui.small_button("⤓ Latest").clicked() { jump_to_latest = Some(true); }
+                ui.add_space(8.0);
+                egui::ComboBox::from_id_source("export_format")
+                    .selected_text(self.state.export_format.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.state.export_format, ExportFormat::HexLines, "Hex lines");
+                        ui.selectable_value(&mut self.state.export_format, ExportFormat::Binary, "Binary");
+                        ui.selectable_value(&mut self.state.export_format, ExportFormat::Csv, "CSV");
+                    });
+                if ui.button("Export messages").clicked() {
+                    let format = self.state.export_format;
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_file_name(format!("messages.{}", format.extension()))
+                        .save_file()
+                    {
+                        match export_messages(&path, &self.state.received_messages, format) {
+                            Ok(()) => info!("exported {} messages to {}", self.state.received_messages.len(), path.display()),
+                            Err(e) => error!("export error: {}", e),
+                        }
+                    }
+                }
+                if ui.button("Export pcap").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .set_file_name("messages.pcap")
+                        .save_file()
+                    {
+                        let packets = self.state.received_messages.iter().map(|m| (m.wall_clock.into(), m.bytes.as_slice()));
+                        match export_pcap(&path, packets, LINKTYPE_USER0) {
+                            Ok(()) => info!("exported pcap to {}", path.display()),
+                            Err(e) => error!("pcap export error: {}", e),
+                        }
+                    }
+                }
+                if ui.button("Import hexdump").on_hover_text("Frame a text hex dump with the current delimiters").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| parse_hex_dump(&text)) {
+                            Ok(mut bytes) => {
+                                let mut frames = self.state.engine.frame(&mut bytes);
+                                frames.extend(flush_partial(&mut bytes, &self.state.engine.start_bytes));
+                                let skipped: usize = frames.iter().map(|f| f.skipped).sum();
+                                info!("imported {} messages from {} ({} bytes skipped)", frames.len(), path.display(), skipped);
+                                for framed in frames {
+                                    let result = self.state.engine.evaluate(framed);
+                                    self.state.push_message(result);
+                                }
                             }
-                            // Suspected data warnings
-                    let active_label = find_message_label(msg, &self.state.label_rules);
-                            let warnings = check_suspects_for_message(msg, &active_label, &self.state.suspect_rules);
-                    let mut critical = false;
-                    for (sev, w) in warnings {
-                        let _ = match sev {
-                            app::suspects::Severity::Info => ui.label(format!("Note: {}", w)),
-                            app::suspects::Severity::Warning => ui.colored_label(egui::Color32::YELLOW, format!("Warning: {}", w)),
-                            app::suspects::Severity::Critical => { critical = true; ui.colored_label(egui::Color32::RED, format!("CRITICAL: {}", w)) }
-                        };
+                            Err(e) => error!("import {} failed: {}", path.display(), e),
+                        }
                     }
-                    any_critical = any_critical || critical;
-                            if !self.state.watch_items.is_empty() {
-                                ui.add_space(8.0);
-                                ui.separator();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Label");
+                egui::ComboBox::from_id_source("label_filter")
+                    .selected_text(self.state.label_filter.clone().unwrap_or_else(|| "All".to_string()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.state.label_filter, None, "All");
+                        for rule in &self.state.engine.label_rules {
+                            ui.selectable_value(&mut self.state.label_filter, Some(rule.name.clone()), rule.name.clone());
+                        }
+                    });
+                ui.checkbox(&mut self.state.pinned_only, "Pinned only");
+                ui.separator();
+                ui.label("Filter");
+                egui::ComboBox::from_id_source("message_filter_mode")
+                    .selected_text(self.state.message_filter_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in [FilterMode::HexContains, FilterMode::TextContains, FilterMode::LabelEquals] {
+                            ui.selectable_value(&mut self.state.message_filter_mode, mode, mode.to_string());
+                        }
+                    });
+                let hint = match self.state.message_filter_mode {
+                    FilterMode::HexContains => "e.g. 0A FF",
+                    FilterMode::TextContains => "e.g. ERR",
+                    FilterMode::LabelEquals => "label name",
+                };
+                ui.add(egui::TextEdit::singleline(&mut self.state.message_filter).hint_text(hint).desired_width(140.0));
+                if !self.state.message_filter.is_empty() && ui.small_button("✖").on_hover_text("Clear filter").clicked() {
+                    self.state.message_filter.clear();
+                }
+                ui.separator();
+                ui.checkbox(&mut self.state.collapse_duplicates, "Collapse duplicates");
+                ui.add_enabled_ui(self.state.collapse_duplicates, |ui| {
+                    egui::ComboBox::from_id_source("dedup_scope")
+                        .selected_text(self.state.dedup_scope.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.state.dedup_scope, DedupScope::Consecutive, DedupScope::Consecutive.to_string());
+                            ui.selectable_value(&mut self.state.dedup_scope, DedupScope::Recent, DedupScope::Recent.to_string());
+                        });
+                });
+            });
+            egui::CollapsingHeader::new("Traffic mix").id_source("traffic_mix").show(ui, |ui| {
+                if self.state.label_stats.is_empty() {
+                    ui.weak("No messages yet");
+                    return;
+                }
+                if self.state.dropped_messages > 0 {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("{} older messages dropped by the cap", self.state.dropped_messages));
+                }
+                let now = std::time::Instant::now();
+                let mut mix: Vec<(String, u64, f64)> = self
+                    .state
+                    .label_stats
+                    .iter_mut()
+                    .map(|(name, stats)| (name.clone(), stats.count, stats.rate(now)))
+                    .collect();
+                mix.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                ui.horizontal_wrapped(|ui| {
+                    for (i, (name, count, rate)) in mix.iter().enumerate() {
+                        if i > 0 { ui.weak("·"); }
+                        let color = self
+                            .state
+                            .engine
+                            .label_rules
+                            .iter()
+                            .find(|r| &r.name == name)
+                            .map(|r| egui::Color32::from_rgb(r.color[0], r.color[1], r.color[2]))
+                            .unwrap_or(ui.visuals().text_color());
+                        ui.colored_label(color, format!("{}: {} ({:.1}/s)", name, count, rate));
+                    }
+                });
+            });
+            if let Some((start, end)) = self.state.selected_range {
+                ui.horizontal(|ui| {
+                    if start == end {
+                        ui.label(format!("Selected byte {}", start));
+                    } else {
+                        ui.label(format!("Selected bytes {} ({} bytes)", format_index_range(start, end), end - start + 1));
+                    }
+                    if ui.small_button("Use as watch range").clicked() {
+                        self.state.use_selected_range(LeftPanelTab::Watch);
+                    }
+                    if ui.small_button("Use as label range").clicked() {
+                        self.state.use_selected_range(LeftPanelTab::Labels);
+                    }
+                    if ui.small_button("Use as expectation range").clicked() {
+                        self.state.use_selected_range(LeftPanelTab::Suspects);
+                    }
+                    if ui.small_button("✖").on_hover_text("Clear the selection").clicked() {
+                        self.state.selected_range = None;
+                        self.state.range_click_anchor = None;
+                    }
+                });
+            }
+            egui::CollapsingHeader::new(format!("Unframed buffer ({} bytes)", self.state.engine.buffer.len()))
+                .id_source("unframed_buffer")
+                .show(ui, |ui| {
+                    if self.state.engine.buffer.is_empty() {
+                        ui.weak("Empty: every received byte is part of a frame");
+                        return;
+                    }
+                    let shown = &self.state.engine.buffer[self.state.engine.buffer.len().saturating_sub(UNFRAMED_PREVIEW_BYTES)..];
+                    if shown.len() < self.state.engine.buffer.len() {
+                        ui.weak(format!("Last {} bytes", shown.len()));
+                    }
+                    egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                        ui.monospace(format_hex_rows(shown, self.state.engine.unit_size, Endian::Big, self.state.hex_uppercase));
+                    });
+                });
+            // Only the cards in view are built, placed by each card's height when last drawn
+            let visible: Vec<usize> = (0..self.state.received_messages.len())
+                .filter(|&i| {
+                    let entry = &self.state.received_messages[i];
+                    let msg = &entry.bytes;
+                    if self.state.pinned_only && !entry.pinned { return false; }
+                    if self.state.label_filter.is_some() && entry.label != self.state.label_filter { return false; }
+                    message_matches_filter(msg, &entry.label, &self.state.message_filter, self.state.message_filter_mode)
+                })
+                .collect();
+            let spacing = ui.spacing().item_spacing.y;
+            // Top of each visible row, then the bottom of the list; cards not drawn yet are estimated
+            let mut tops = Vec::with_capacity(visible.len() + 1);
+            let mut y = 0.0;
+            for &i in &visible {
+                tops.push(y);
+                let height = self.state.received_messages[i].card_height;
+                y += if height > 0.0 { height } else { ESTIMATED_CARD_HEIGHT } + spacing;
+            }
+            tops.push(y);
+            let total_height = (y - spacing).max(0.0);
+            let mut card_heights: Vec<(usize, f32)> = Vec::new();
+            let mut diff_clicked: Option<usize> = None;
+            let mut pin_clicked: Option<usize> = None;
+            let mut note_clicked: Option<usize> = None;
+            // Message whose note draft was saved (`true`) or discarded
+            let mut note_done: Option<(usize, bool)> = None;
+            // Byte index clicked in a hex body, and whether shift was held
+            let mut byte_clicked: Option<(usize, bool)> = None;
+            let mut scroll = egui::ScrollArea::vertical().auto_shrink([false, false]).stick_to_bottom(self.state.autoscroll);
+            if let Some(latest) = jump_to_latest {
+                scroll = scroll.vertical_scroll_offset(if latest { total_height } else { 0.0 });
+            }
+            scroll.show_viewport(ui, |ui, viewport| {
+                ui.set_height(total_height);
+                // First row whose bottom edge is below the top of the viewport
+                let first = tops[1..].partition_point(|&bottom| bottom <= viewport.min.y);
+                let rect = egui::Rect::from_x_y_ranges(ui.max_rect().x_range(), ui.max_rect().top() + tops[first]..=ui.max_rect().top() + viewport.max.y);
+                ui.allocate_ui_at_rect(rect, |ui| {
+                    // Keep IDs stable as rows scroll in and out
+                    ui.skip_ahead_auto_ids(first);
+                    for (&i, &top) in visible.iter().zip(&tops).skip(first) {
+                        if top > viewport.max.y { break; }
+                        let entry = &self.state.received_messages[i];
+                        let msg = &entry.bytes;
+                        let active_label = &entry.label;
+                        let label_rule = active_label.as_ref().and_then(|name| self.state.engine.label_rules.iter().find(|r| &r.name == name));
+                        let warnings = &entry.warnings;
+                        let is_critical = entry.is_critical();
+                        // Critical alerts take precedence over the label tint.
+                        let tint = if is_critical {
+                            Some(egui::Color32::RED)
+                        } else {
+                            label_rule.map(|r| egui::Color32::from_rgb(r.color[0], r.color[1], r.color[2]))
+                        };
+                        let mut frame = egui::Frame::group(ui.style())
+                            .outer_margin(egui::Margin::symmetric(0.0, 4.0))
+                            .inner_margin(egui::Margin::symmetric(12.0, 10.0));
+                        if let Some(color) = tint {
+                            frame = frame.stroke(egui::Stroke::new(1.5, color));
+                        }
+                        let card = frame
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    let status_color = match entry.status {
+                                        FrameStatus::Clean => egui::Color32::from_rgb(0x4c, 0xaf, 0x50),
+                                        FrameStatus::Flushed => egui::Color32::from_rgb(0xff, 0xa7, 0x26),
+                                        FrameStatus::NoEndDelimiter => egui::Color32::GRAY,
+                                        FrameStatus::ChecksumFail => egui::Color32::RED,
+                                    };
+                                    ui.colored_label(status_color, "●").on_hover_text(entry.status.to_string());
+                                    let star = if entry.pinned { "★" } else { "☆" };
+                                    if ui.selectable_label(entry.pinned, star).on_hover_text("Pin: keep this message when the list is full").clicked() {
+                                        pin_clicked = Some(i);
+                                    }
+                                    match active_label {
+                                        Some(name) => ui.colored_label(tint.unwrap_or(ui.visuals().strong_text_color()), egui::RichText::new(name).strong()),
+                                        None => ui.strong(format!("Message {}", i + 1)),
+                                    };
+                                    ui.weak(format!("#{:04X}", entry.fingerprint >> 16))
+                                        .on_hover_text(format!("CRC-32 {:08X}; identical frames share this fingerprint", entry.fingerprint));
+                                    ui.label(entry.wall_clock.format("%H:%M:%S%.3f").to_string())
+                                        .on_hover_text(entry.wall_clock.format("%Y-%m-%d %H:%M:%S%.6f %:z").to_string());
+                                    if let Some(prev) = i.checked_sub(1).and_then(|p| self.state.received_messages.get(p)) {
+                                        ui.weak(format_delta(entry.received_at.saturating_duration_since(prev.received_at)))
+                                            .on_hover_text("Time since the previous message");
+                                    }
+                                    if self.state.engine.start_bytes.len() > 1 {
+                                        if let Some(pattern) = entry.start_pattern.and_then(|p| self.state.engine.start_bytes.get(p)) {
+                                            ui.weak(format!("sync {}", format_hex_rows(pattern, 1, Endian::Big, self.state.hex_uppercase)));
+                                        }
+                                    }
+                                    if entry.repeat > 1 {
+                                        ui.strong(format!("(×{})", entry.repeat)).on_hover_text("Identical frames collapsed into this entry");
+                                    }
+                                    ui.add_space(8.0);
+                                    ui.label(format!("{} bytes", msg.len()));
+                                    let selected = self.state.selected_for_diff.contains(&i);
+                                    if ui.selectable_label(selected, "Diff").on_hover_text("Select two messages to compare").clicked() {
+                                        diff_clicked = Some(i);
+                                    }
+                                    if ui.button("Note").on_hover_text("Annotate this message; pin it to keep the note when the list is full").clicked() {
+                                        note_clicked = Some(i);
+                                    }
+                                    ui.menu_button("Copy", |ui| {
+                                        if ui.button("Copy hex").clicked() {
+                                            ui.output_mut(|o| o.copied_text = encode_hex(msg, self.state.hex_uppercase));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Copy as 0x-array").clicked() {
+                                            ui.output_mut(|o| o.copied_text = format_bytes_as_array(msg, self.state.hex_uppercase));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Copy as text").clicked() {
+                                            ui.output_mut(|o| o.copied_text = String::from_utf8_lossy(msg).to_string());
+                                            ui.close_menu();
+                                        }
+                                    });
+                                });
                                 ui.add_space(6.0);
-                                    egui::Grid::new(format!("watch_grid_{}", i))
-                                        .striped(true)
-                                        .num_columns(3)
-                                        .show(ui, |ui| {
-                                        let active_label = find_message_label(msg, &self.state.label_rules);
-                                        for w in &self.state.watch_items {
-                                            let target_applies = match (&w.target, &active_label) {
-                                                (WatchTarget::All, _) => true,
-                                                (WatchTarget::Label(name), Some(lbl)) => name == lbl,
-                                                (WatchTarget::Label(_), None) => false,
-                                            };
-                                            if !target_applies { continue; }
-                                            let start = w.start_index;
-                                            let end = w.end_index;
-                                            let slice = if start <= end && end < msg.len() { Some(&msg[start..=end]) } else { None };
-                                            let value_str = match slice {
-                                                Some(bytes) => match w.view {
-                                                    WatchView::Hex => format!("0x{}", hex::encode_upper(bytes)),
-                                                    WatchView::Text => format_bytes_for_view(bytes, WatchView::Text),
-                                                    WatchView::Binary => format_bytes_for_view(bytes, WatchView::Binary),
-                                                },
-                                                None => "-".to_string(),
-                                            };
-                                            ui.label(&w.name);
-                                            ui.monospace(format!("[{}..{}] {}", start, end, w.view));
-                                            ui.monospace(value_str);
-                                            ui.end_row();
+                                match self.state.display_mode {
+                                    DisplayMode::Hex => {
+                                        // The clicked selection is listed first so it shows over watch highlights
+                                        let selection = self.state.selected_range.map(|(s, e)| (s, e, ui.visuals().selection.stroke.color));
+                                        let ranges: Vec<(usize, usize, egui::Color32)> = selection
+                                            .into_iter()
+                                            .chain(
+                                                self.state.engine.watch_items
+                                                    .iter()
+                                                    .enumerate()
+                                                    .filter(|(_, w)| target_applies(&w.target, active_label, entry.index))
+                                                    .map(|(wi, w)| (w.start_index, w.end_index, watch_highlight_color(wi))),
+                                            )
+                                            .collect();
+                                        if self.state.show_hex_ruler {
+                                            ui.label(egui::RichText::new(format_hex_ruler(msg.len().min(HEX_ROW_BYTES), self.state.engine.unit_size)).monospace().weak());
+                                        }
+                                        let (job, byte_of_char) = highlighted_hex_job(ui, msg, self.state.engine.unit_size, self.state.hex_uppercase, &ranges);
+                                        if let Some(byte) = clickable_hex(ui, job, &byte_of_char) {
+                                            byte_clicked = Some((byte, ui.input(|i| i.modifiers.shift)));
+                                        }
+                                    }
+                                    DisplayMode::Text => {
+                                        if self.state.escape_text {
+                                            ui.monospace(format_escaped_text(msg));
+                                        } else {
+                                            ui.monospace(format_text(msg));
+                                        }
+                                    }
+                                    DisplayMode::HexDump => { ui.monospace(format_hex_dump(msg, self.state.hexdump_width, self.state.hex_uppercase)); }
+                                    DisplayMode::ControlPictures => { ui.monospace(format_control_pictures(msg)); }
+                                }
+                                if self.state.note_editing == Some(entry.index) {
+                                    ui.horizontal(|ui| {
+                                        let edit = ui.add(egui::TextEdit::singleline(&mut self.state.note_draft).hint_text("Note"));
+                                        if ui.button("Save").clicked() || (edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                                            note_done = Some((i, true));
+                                        }
+                                        if ui.button("Cancel").clicked() {
+                                            note_done = Some((i, false));
                                         }
                                     });
-                            }
-                        });
-                }
-                // Update global critical state based on this frame's evaluation across all messages
-                self.state.critical_active = any_critical;
+                                } else if !entry.note.is_empty() {
+                                    ui.label(egui::RichText::new(format!("📝 {}", entry.note)).italics());
+                                }
+                                // Suspected data warnings
+                                for (sev, w) in warnings {
+                                    let _ = match sev {
+                                        app::suspects::Severity::Info => ui.label(format!("Note: {}", w)),
+                                        app::suspects::Severity::Warning => ui.colored_label(egui::Color32::YELLOW, format!("Warning: {}", w)),
+                                        app::suspects::Severity::Critical => ui.colored_label(egui::Color32::RED, format!("CRITICAL: {}", w)),
+                                    };
+                                }
+                                if !self.state.engine.watch_items.is_empty() {
+                                    ui.add_space(8.0);
+                                    ui.separator();
+                                    ui.add_space(6.0);
+                                        egui::Grid::new(format!("watch_grid_{}", i))
+                                            .striped(true)
+                                            .num_columns(4)
+                                            .show(ui, |ui| {
+                                            for (wi, w) in self.state.engine.watch_items.iter().enumerate() {
+                                                if !target_applies(&w.target, active_label, entry.index) { continue; }
+                                                let slice = resolve_range(w.start_index, w.end_index, msg.len()).map(|(s, e)| &msg[s..=e]);
+                                                let value_str = match slice {
+                                                    Some(bytes) if self.state.escape_text && w.view == WatchView::Text => format_escaped_text(bytes),
+                                                    Some(bytes) => w.format_value(bytes, self.state.engine.unit_size, self.state.hex_uppercase),
+                                                    None => format!("out of range (len {})", msg.len()),
+                                                };
+                                                ui.colored_label(watch_highlight_color(wi), &w.name);
+                                                ui.monospace(format!("[{}] {}", format_index_range(w.start_index, w.end_index), w.view));
+                                                if slice.is_some() {
+                                                    ui.monospace(&value_str);
+                                                } else {
+                                                    ui.weak(&value_str);
+                                                }
+                                                ui.horizontal(|ui| {
+                                                    if let Some(bytes) = slice {
+                                                        if ui.small_button("📋").on_hover_text("Copy value").clicked() {
+                                                            ui.output_mut(|o| o.copied_text = value_str.clone());
+                                                        }
+                                                        if let Some(number) = w.raw_number(bytes) {
+                                                            if ui.small_button("#").on_hover_text(format!("Copy number ({})", number)).clicked() {
+                                                                ui.output_mut(|o| o.copied_text = number.to_string());
+                                                            }
+                                                        }
+                                                    }
+                                                });
+                                                ui.end_row();
+                                            }
+                                        });
+                                }
+                            });
+                        card_heights.push((i, card.response.rect.height()));
+                    }
+                });
             });
+            for (i, height) in card_heights {
+                if let Some(entry) = self.state.received_messages.get_mut(i) {
+                    entry.card_height = height;
+                }
+            }
+            if let Some(entry) = pin_clicked.and_then(|i| self.state.received_messages.get_mut(i)) {
+                entry.pinned = !entry.pinned;
+                self.state.trim_messages();
+            }
+            if let Some(i) = diff_clicked {
+                self.state.toggle_diff_selection(i);
+            }
+            if let Some(entry) = note_clicked.and_then(|i| self.state.received_messages.get(i)) {
+                self.state.note_editing = Some(entry.index);
+                self.state.note_draft = entry.note.clone();
+            }
+            if let Some((i, save)) = note_done {
+                if let Some(entry) = self.state.received_messages.get_mut(i).filter(|_| save) {
+                    entry.note = self.state.note_draft.trim().to_string();
+                }
+                self.state.note_editing = None;
+            }
+            if let Some((byte, extend)) = byte_clicked {
+                let (start, end) = match (extend, self.state.range_click_anchor) {
+                    (true, Some(anchor)) => (anchor.min(byte), anchor.max(byte)),
+                    _ => {
+                        self.state.range_click_anchor = Some(byte);
+                        (byte, byte)
+                    }
+                };
+                self.state.selected_range = Some((start, end));
+                self.state.set_active_form_range(format_index_range(start, end));
+            }
         });
 
+        if let [a, b] = self.state.selected_for_diff[..] {
+            let mut open = true;
+            egui::Window::new(format!("Diff: message {} vs {}", a + 1, b + 1))
+                .open(&mut open)
+                .default_width(620.0)
+                .show(ctx, |ui| {
+                    let (Some(ma), Some(mb)) = (self.state.received_messages.get(a), self.state.received_messages.get(b)) else { return };
+                    let differing = (0..ma.bytes.len().max(mb.bytes.len())).filter(|&k| ma.bytes.get(k) != mb.bytes.get(k)).count();
+                    ui.label(format!("{} vs {} bytes, {} positions differ", ma.bytes.len(), mb.bytes.len(), differing));
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        diff_grid(ui, &ma.bytes, &mb.bytes, self.state.hex_uppercase);
+                    });
+                });
+            if !open {
+                self.state.selected_for_diff.clear();
+            }
+        }
+
         // Removed bottom send bar; sending controls are now in the top toolbar
     }
 }
 
+/// Distinct color for the watch at `index`, used for its grid name and hex highlight.
+fn watch_highlight_color(index: usize) -> egui::Color32 {
+    const PALETTE: [(u8, u8, u8); 6] = [
+        (80, 160, 255),
+        (255, 160, 60),
+        (120, 210, 120),
+        (220, 110, 220),
+        (240, 220, 80),
+        (90, 210, 210),
+    ];
+    let (r, g, b) = PALETTE[index % PALETTE.len()];
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// Lay out a message's hex bytes, boxing each byte covered by one of the watch `ranges`.
+///
+/// Also returns the byte index for every character of the text, separators counting
+/// towards the byte before them.
+fn highlighted_hex_job(ui: &egui::Ui, msg: &[u8], unit_size: usize, uppercase: bool, ranges: &[(usize, usize, egui::Color32)]) -> (egui::text::LayoutJob, Vec<usize>) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+    let unit = unit_size.max(1);
+    let mut job = egui::text::LayoutJob::default();
+    let mut byte_of_char = Vec::with_capacity(msg.len() * 3);
+    for (i, b) in msg.iter().enumerate() {
+        byte_of_char.extend([i, i, i]);
+        let highlight = ranges.iter().find(|(s, e, _)| *s <= i && i <= *e).map(|(_, _, c)| *c);
+        let format = match highlight {
+            Some(color) => egui::TextFormat {
+                font_id: font_id.clone(),
+                color,
+                background: color.gamma_multiply(0.25),
+                underline: egui::Stroke::new(1.0, color),
+                ..Default::default()
+            },
+            None => egui::TextFormat { font_id: font_id.clone(), color: text_color, ..Default::default() },
+        };
+        job.append(&encode_hex(&[*b], uppercase), 0.0, format);
+        if i + 1 < msg.len() && (i + 1) % HEX_ROW_BYTES == 0 {
+            job.append("\n", 0.0, egui::TextFormat { font_id: font_id.clone(), color: text_color, ..Default::default() });
+        } else if i + 1 < msg.len() && (i + 1) % unit == 0 {
+            job.append(" ", 0.0, egui::TextFormat { font_id: font_id.clone(), color: text_color, ..Default::default() });
+        } else {
+            // No separator after this byte
+            byte_of_char.pop();
+        }
+    }
+    (job, byte_of_char)
+}
+
+/// Show a hex layout and return the byte index under the pointer when it is clicked.
+fn clickable_hex(ui: &mut egui::Ui, job: egui::text::LayoutJob, byte_of_char: &[usize]) -> Option<usize> {
+    let galley = ui.fonts(|f| f.layout_job(job));
+    let (rect, response) = ui.allocate_exact_size(galley.size(), egui::Sense::click());
+    ui.painter().galley(rect.min, galley.clone(), ui.visuals().text_color());
+    let response = response
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+        .on_hover_text("Click a byte to select it and put its index in the open range field; shift-click to extend");
+    if !response.clicked() { return None; }
+    let pos = response.interact_pointer_pos()?;
+    let cursor = galley.cursor_from_pos(pos - rect.min);
+    byte_of_char.get(cursor.ccursor.index).or(byte_of_char.last()).copied()
+}
+
+/// Bytes per row in the diff view.
+const DIFF_ROW_BYTES: usize = 16;
+
+/// Render two messages aligned byte by byte, differing positions in red; the shorter is padded with `--`.
+fn diff_grid(ui: &mut egui::Ui, a: &[u8], b: &[u8], uppercase: bool) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+    let diff_color = ui.visuals().error_fg_color;
+    let row_job = |mine: &[u8], other: &[u8], start: usize| {
+        let mut job = egui::text::LayoutJob::default();
+        for k in start..(start + DIFF_ROW_BYTES).min(mine.len().max(other.len())) {
+            let text = mine.get(k).map_or("--".to_string(), |b| encode_hex(&[*b], uppercase));
+            let color = if mine.get(k) != other.get(k) { diff_color } else { text_color };
+            job.append(&text, 0.0, egui::TextFormat { font_id: font_id.clone(), color, ..Default::default() });
+            job.append(" ", 0.0, egui::TextFormat { font_id: font_id.clone(), color: text_color, ..Default::default() });
+        }
+        job
+    };
+    egui::Grid::new("diff_grid").striped(true).num_columns(3).show(ui, |ui| {
+        ui.strong("Offset");
+        ui.strong("A");
+        ui.strong("B");
+        ui.end_row();
+        for start in (0..a.len().max(b.len())).step_by(DIFF_ROW_BYTES) {
+            ui.monospace(format!("{:04X}", start));
+            ui.label(row_job(a, b, start));
+            ui.label(row_job(b, a, start));
+            ui.end_row();
+        }
+    });
+}
+
+/// Combo box for choosing how a watch renders its bytes.
+fn watch_view_picker(ui: &mut egui::Ui, id: impl std::hash::Hash, width: f32, view: &mut WatchView) {
+    egui::ComboBox::from_id_source(id)
+        .width(width)
+        .selected_text(view.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(view, WatchView::Hex, "Hex");
+            ui.selectable_value(view, WatchView::Text, "Text");
+            ui.selectable_value(view, WatchView::Binary, "Binary");
+            ui.selectable_value(view, WatchView::Enum, "Enum");
+            ui.selectable_value(view, WatchView::SignedByte, "Signed bytes");
+            ui.separator();
+            for width in [2, 4, 8] {
+                for signed in [false, true] {
+                    let integer = WatchView::Integer { width, signed };
+                    ui.selectable_value(view, integer, integer.to_string())
+                        .on_hover_text(format!("{}-byte {} integer in decimal, in the watch's byte order", width, if signed { "signed" } else { "unsigned" }));
+                }
+            }
+            ui.separator();
+            for algo in ChecksumAlgo::ALL {
+                ui.selectable_value(view, WatchView::Checksum(algo), format!("Checksum ({})", algo));
+            }
+        });
+}
+
+/// "Every Nth message" entry for a target combo box; picking it starts at every 2nd from #0.
+fn every_nth_option(ui: &mut egui::Ui, target: &mut WatchTarget) {
+    let selected = matches!(target, WatchTarget::EveryNth { .. });
+    if ui.selectable_label(selected, "Every Nth message").clicked() && !selected {
+        *target = WatchTarget::EveryNth { n: 2, offset: 0 };
+    }
+}
+
+/// Step and start fields shown under a target combo box set to `EveryNth`.
+fn every_nth_fields(ui: &mut egui::Ui, target: &mut WatchTarget) {
+    if let WatchTarget::EveryNth { n, offset } = target {
+        ui.horizontal(|ui| {
+            ui.label("Every");
+            ui.add(egui::DragValue::new(n).clamp_range(1..=u64::MAX));
+            ui.label("starting at #");
+            ui.add(egui::DragValue::new(offset));
+        });
+    }
+}
+
+/// "Checksum" entry for an expected-kind combo box; picking it starts with CRC-16/CCITT.
+fn checksum_kind_option(ui: &mut egui::Ui, kind: &mut ExpectedKind) {
+    let selected = matches!(kind, ExpectedKind::Checksum { .. });
+    if ui.selectable_label(selected, "Checksum").clicked() && !selected {
+        *kind = ExpectedKind::Checksum { algo: ChecksumAlgo::Crc16Ccitt, over: (0, 0), stored_at: (0, 0) };
+    }
+}
+
+/// Algorithm and "Stored at" fields shown in place of the expected value for a `Checksum`
+/// kind; returns whether they were shown. The rule's own range is what gets summed.
+fn checksum_kind_fields(ui: &mut egui::Ui, id: impl std::hash::Hash, width: f32, kind: &mut ExpectedKind, stored: &mut String) -> bool {
+    let ExpectedKind::Checksum { algo, .. } = kind else { return false };
+    ui.label("Algorithm");
+    egui::ComboBox::from_id_source(id).width(width)
+        .selected_text(algo.to_string())
+        .show_ui(ui, |ui| {
+            for a in ChecksumAlgo::ALL {
+                ui.selectable_value(algo, a, a.to_string());
+            }
+        });
+    ui.label("Stored at");
+    ui.add_sized([width, 0.0], egui::TextEdit::singleline(stored).hint_text(format!("e.g. 20-{} ({} byte(s), either byte order)", 19 + algo.width(), algo.width())));
+    true
+}
+
+/// A form's expected kind with a `Checksum` kind's ranges filled in: it sums the rule's
+/// range `over` and reads the stored value from the "Stored at" field.
+fn form_expected_kind(kind: ExpectedKind, over: (usize, usize), stored: &str) -> Result<ExpectedKind, String> {
+    match kind {
+        ExpectedKind::Checksum { algo, .. } => Ok(ExpectedKind::Checksum { algo, over, stored_at: parse_range_field(stored)? }),
+        kind => Ok(kind),
+    }
+}
+
+/// Hex / ASCII switch for a delimiter field.
+fn pattern_mode_toggle(ui: &mut egui::Ui, ascii: &mut bool) {
+    ui.selectable_value(ascii, false, "Hex");
+    ui.selectable_value(ascii, true, "ASCII")
+        .on_hover_text("Literal characters with escapes: \\r \\n \\t \\0 \\\\ \\xNN (\\x2C for a comma)");
+}
+
+/// Swap item `i` with its neighbour above or below, keeping an in-progress edit on the same item.
+fn move_item<T>(items: &mut [T], i: usize, up: bool, editing: &mut Option<usize>) {
+    let j = if up { i.checked_sub(1) } else { Some(i + 1) };
+    let Some(j) = j.filter(|&j| j < items.len()) else { return };
+    items.swap(i, j);
+    if *editing == Some(i) {
+        *editing = Some(j);
+    } else if *editing == Some(j) {
+        *editing = Some(i);
+    }
+}
+
+/// Draw recent values as a small line chart scaled to their own min/max.
+fn sparkline(ui: &mut egui::Ui, values: &std::collections::VecDeque<f64>, color: egui::Color32) {
+    let (response, painter) = ui.allocate_painter(egui::vec2(100.0, 20.0), egui::Sense::hover());
+    let rect = response.rect;
+    if values.len() < 2 { return; }
+    let (min, max) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+    let span = if max > min { max - min } else { 1.0 };
+    let step = rect.width() / (values.len() - 1) as f32;
+    let points: Vec<egui::Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| egui::pos2(rect.left() + i as f32 * step, rect.bottom() - ((v - min) / span) as f32 * rect.height()))
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+    response.on_hover_text(format!("last {} values, {} – {}", values.len(), min, max));
+}
+
+/// Show a form field's parse error in red beneath it, if there is one.
+fn field_error(ui: &mut egui::Ui, error: &Option<String>) {
+    if let Some(e) = error {
+        ui.colored_label(ui.visuals().error_fg_color, e);
+    }
+}
+
+/// Render the editable condition rows of a label rule form, with add/remove controls.
+fn label_condition_rows(ui: &mut egui::Ui, conditions: &mut Vec<LabelConditionForm>) {
+    let mut to_remove: Option<usize> = None;
+    let can_remove = conditions.len() > 1;
+    for (ci, cond) in conditions.iter_mut().enumerate() {
+        ui.push_id(ci, |ui| {
+            egui::Frame::group(ui.style())
+                .inner_margin(egui::Margin::symmetric(8.0, 6.0))
+                .outer_margin(egui::Margin::symmetric(0.0, 4.0))
+                .show(ui, |ui| {
+                    let w = ui.available_width();
+                    ui.set_width(w);
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Condition {}", ci + 1));
+                        if can_remove && ui.small_button("Remove").clicked() {
+                            to_remove = Some(ci);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut cond.mode, LabelMatchMode::Exact, "Exact bytes");
+                        ui.radio_value(&mut cond.mode, LabelMatchMode::Regex, "Regex");
+                    });
+                    ui.label("Index or range");
+                    let range_hint = match cond.mode { LabelMatchMode::Exact => "e.g. 3 or 3-4", LabelMatchMode::Regex => "blank = whole message" };
+                    ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut cond.range).hint_text(range_hint));
+                    match cond.mode {
+                        LabelMatchMode::Exact => {
+                            ui.label("Value hex");
+                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut cond.value_hex).hint_text("e.g. 01 or AA BB"));
+                            ui.label("Mask hex (optional)");
+                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut cond.mask_hex).hint_text("e.g. F0; blank = FF"));
+                        }
+                        LabelMatchMode::Regex => {
+                            ui.label("Regex");
+                            ui.add_sized([w, 0.0], egui::TextEdit::singleline(&mut cond.pattern).hint_text("e.g. PING|PONG"));
+                        }
+                    }
+                });
+        });
+    }
+    if let Some(ci) = to_remove {
+        conditions.remove(ci);
+    }
+    if ui.button("Add condition").clicked() {
+        conditions.push(LabelConditionForm::default());
+    }
+}
+
+/// Interactive viewer for framed byte streams.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Read the byte stream from stdin instead of connecting over TCP.
+    #[arg(long)]
+    stdin: bool,
+}
+
 fn main() -> eframe::Result<()> {
     env_logger::init();
-    let options = eframe::NativeOptions::default();
+    let args = Args::parse();
+    let options = eframe::NativeOptions {
+        // Reopen at the last window position and size
+        persist_window: true,
+        ..Default::default()
+    };
     eframe::run_native(
         "ByteBuster",
         options,
-        Box::new(|_cc| Box::new(ByteBusterApp::default())),
+        Box::new(move |cc| Box::new(ByteBusterApp::new(cc, &args))),
     )
 }
@@ -1,9 +1,72 @@
 //! Simple TCP test server that streams framed messages and responds to a trigger.
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+use byte_buster::app::recording::{read_records, SESSION_MAGIC};
+use clap::Parser;
+
+/// Demo server streaming `AA 55 ... 0D 0A` frames to every client.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:9000")]
+    bind: String,
+    /// Seconds between periodic frames.
+    #[arg(long, default_value_t = 30)]
+    interval: u64,
+    /// Text file with one message per line to cycle through (ids 1, 2, 3, ...). Lines are
+    /// at most 21 bytes and the file at most 255 lines.
+    #[arg(long)]
+    messages: Option<PathBuf>,
+    /// Stream this capture to each client instead of synthetic frames. Raw bytes, or a
+    /// session recorded in the app (its chunks are sent back to back).
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Replay throughput limit; 0 sends the whole capture at once.
+    #[arg(long, default_value_t = 0)]
+    bytes_per_sec: usize,
+    /// Start the capture over when it ends instead of closing the connection.
+    #[arg(long = "loop")]
+    loop_replay: bool,
+}
+
+/// Load a capture, unpacking the chunks of an app session recording.
+fn load_capture(path: &Path) -> std::io::Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    let Some(records) = data.strip_prefix(SESSION_MAGIC.as_slice()) else { return Ok(data) };
+    Ok(read_records(records)?.into_iter().flat_map(|(_, chunk)| chunk).collect())
+}
+
+/// Stream `capture` to the client at `bytes_per_sec`, looping if asked.
+fn replay_to_client(mut stream: TcpStream, capture: Arc<Vec<u8>>, bytes_per_sec: usize, loop_replay: bool) -> std::io::Result<()> {
+    const TICK: Duration = Duration::from_millis(100);
+    let per_tick = if bytes_per_sec == 0 { capture.len().max(1) } else { (bytes_per_sec / 10).max(1) };
+    loop {
+        for chunk in capture.chunks(per_tick) {
+            stream.write_all(chunk)?;
+            stream.flush()?;
+            if bytes_per_sec > 0 {
+                thread::sleep(TICK);
+            }
+        }
+        if !loop_replay {
+            return Ok(());
+        }
+        // Avoid a busy loop on an empty capture
+        thread::sleep(TICK);
+    }
+}
+
+/// Messages sent when no `--messages` file is given.
+const DEFAULT_MESSAGES: [&str; 3] = ["PING FROM SERVER......", "DATA-REQUEST FROM SRV", "DATA-RESPONSE FROMSV"];
+
+/// Payload bytes in a frame; shorter messages are padded with spaces.
+const MESSAGE_LEN: usize = 21;
+
 /// Build a single framed message with fixed layout and the provided `id` and `msg`.
 fn build_frame(id: u8, start_bytes: [u8; 2], end_bytes: [u8; 2], msg: &[u8]) -> Vec<u8> {
     let mut frame = vec![0u8; 35];
@@ -11,9 +74,9 @@ fn build_frame(id: u8, start_bytes: [u8; 2], end_bytes: [u8; 2], msg: &[u8]) ->
     frame[1] = start_bytes[1];
     frame[2] = 0xEE; // garbage
     frame[3] = id;   // id
-    for i in 4..10 { frame[i] = (0xA0 + (i as u8)) as u8; }
-    let mut msg_buf = [b' '; 21];
-    for (i, b) in msg.iter().take(21).enumerate() { msg_buf[i] = *b; }
+    for (i, b) in frame.iter_mut().enumerate().take(10).skip(4) { *b = 0xA0 + i as u8; }
+    let mut msg_buf = [b' '; MESSAGE_LEN];
+    for (slot, b) in msg_buf.iter_mut().zip(msg) { *slot = *b; }
     frame[10..=30].copy_from_slice(&msg_buf);
     frame[31] = 0xF1;
     frame[32] = 0xF2;
@@ -23,16 +86,10 @@ fn build_frame(id: u8, start_bytes: [u8; 2], end_bytes: [u8; 2], msg: &[u8]) ->
 }
 
 /// Handle a single client: periodically sends frames and responds to the trigger `FE ED FA CE` with a burst.
-fn handle_client(mut stream: TcpStream) -> std::io::Result<()> {
+fn handle_client(mut stream: TcpStream, interval: Duration, messages: Arc<Vec<Vec<u8>>>) -> std::io::Result<()> {
     let start_bytes = [0xAAu8, 0x55u8];
     let end_bytes = [0x0Du8, 0x0Au8];
-    let id_cycle: [u8; 3] = [0x01, 0x02, 0x03];
     let mut id_idx: usize = 0;
-    let messages = [
-        b"PING FROM SERVER......".as_ref(),
-        b"DATA-REQUEST FROM SRV".as_ref(),
-        b"DATA-RESPONSE FROMSV".as_ref(),
-    ];
 
     let trigger: [u8; 4] = [0xFE, 0xED, 0xFA, 0xCE]; // FE ED FA CE
     stream.set_read_timeout(Some(Duration::from_millis(100))).ok();
@@ -48,11 +105,9 @@ fn handle_client(mut stream: TcpStream) -> std::io::Result<()> {
                 while let Some(pos) = incoming_buf.windows(trigger.len()).position(|w| w == trigger) {
                     let drain_end = pos + trigger.len();
                     incoming_buf.drain(0..drain_end);
-                    let burst_ids = [0x01u8, 0x02u8, 0x03u8];
-                    let mut out = Vec::with_capacity(35 * burst_ids.len());
-                    for bid in burst_ids {
-                        let m_idx = (bid.saturating_sub(1)) as usize % messages.len();
-                        out.extend_from_slice(&build_frame(bid, start_bytes, end_bytes, messages[m_idx]));
+                    let mut out = Vec::with_capacity(35 * messages.len());
+                    for (m_idx, msg) in messages.iter().enumerate() {
+                        out.extend_from_slice(&build_frame((m_idx + 1) as u8, start_bytes, end_bytes, msg));
                     }
                     let _ = stream.write_all(&out);
                     let _ = stream.flush();
@@ -61,13 +116,12 @@ fn handle_client(mut stream: TcpStream) -> std::io::Result<()> {
             Err(_) => {}
         }
 
-        if last_periodic.elapsed() >= Duration::from_secs(30) {
-            let id = id_cycle[id_idx];
-            let msg = messages[id_idx];
-            let frame = build_frame(id, start_bytes, end_bytes, msg);
+        if last_periodic.elapsed() >= interval {
+            let id = (id_idx + 1) as u8;
+            let frame = build_frame(id, start_bytes, end_bytes, &messages[id_idx]);
             let _ = stream.write_all(&frame);
             let _ = stream.flush();
-            id_idx = (id_idx + 1) % id_cycle.len();
+            id_idx = (id_idx + 1) % messages.len();
             last_periodic = Instant::now();
         }
 
@@ -77,16 +131,52 @@ fn handle_client(mut stream: TcpStream) -> std::io::Result<()> {
     Ok(())
 }
 
-/// Run the server on `127.0.0.1:9000`.
+/// Load the non-empty lines of `path` as messages.
+///
+/// Each line must fit in a frame's payload and there can be at most 255, as the frame id is one byte.
+fn load_messages(path: &Path) -> std::io::Result<Vec<Vec<u8>>> {
+    let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    let text = std::fs::read_to_string(path)?;
+    let messages: Vec<Vec<u8>> = text.lines().filter(|l| !l.trim().is_empty()).map(|l| l.as_bytes().to_vec()).collect();
+    if messages.is_empty() {
+        return Err(invalid("message file has no lines".to_string()));
+    }
+    if messages.len() > u8::MAX as usize {
+        return Err(invalid(format!("message file has {} lines, at most {} fit the one-byte id", messages.len(), u8::MAX)));
+    }
+    if let Some(long) = messages.iter().find(|m| m.len() > MESSAGE_LEN) {
+        return Err(invalid(format!("message '{}' is {} bytes, at most {} fit a frame", String::from_utf8_lossy(long), long.len(), MESSAGE_LEN)));
+    }
+    Ok(messages)
+}
+
+/// Run the server on `--bind` (default `127.0.0.1:9000`).
 fn main() -> std::io::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:9000")?;
-    println!("byte_buster_server listening on 127.0.0.1:9000");
+    let args = Args::parse();
+    let messages = match &args.messages {
+        Some(path) => load_messages(path)?,
+        None => DEFAULT_MESSAGES.iter().map(|m| m.as_bytes().to_vec()).collect(),
+    };
+    let messages = Arc::new(messages);
+    let capture = match &args.replay {
+        Some(path) => Some(Arc::new(load_capture(path)?)),
+        None => None,
+    };
+    let interval = Duration::from_secs(args.interval);
+    let listener = TcpListener::bind(&args.bind)?;
+    println!("byte_buster_server listening on {}", args.bind);
     for incoming in listener.incoming() {
         match incoming {
             Ok(stream) => {
                 println!("client connected: {}", stream.peer_addr().unwrap());
-                thread::spawn(|| {
-                    let _ = handle_client(stream);
+                let messages = Arc::clone(&messages);
+                let capture = capture.clone();
+                let (bytes_per_sec, loop_replay) = (args.bytes_per_sec, args.loop_replay);
+                thread::spawn(move || {
+                    let _ = match capture {
+                        Some(capture) => replay_to_client(stream, capture, bytes_per_sec, loop_replay),
+                        None => handle_client(stream, interval, messages),
+                    };
                 });
             }
             Err(e) => eprintln!("accept error: {}", e),